@@ -0,0 +1,60 @@
+use anyhow::Result;
+use log::info;
+use metis::loader::GraphLoader;
+use metis::routing::MetisRouter;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 默认监听地址
+const DEFAULT_ADDR: &str = "127.0.0.1:8080";
+
+/// Metis HTTP JSON API 服务器
+///
+/// 环境变量：
+/// * `METIS_SERVER_ADDR` - 监听地址，默认 127.0.0.1:8080
+/// * `METIS_TOKEN_LIST` / `METIS_POOL_LIST` - 代币列表和池子列表文件，未设置时使用示例数据
+/// * `METIS_REFRESH_INTERVAL_SECS` - 市场数据刷新间隔（秒），默认取路由器配置
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    info!("🚀 启动 Metis API 服务器");
+
+    let mut router = MetisRouter::new();
+    match (
+        std::env::var("METIS_TOKEN_LIST"),
+        std::env::var("METIS_POOL_LIST"),
+    ) {
+        (Ok(token_list), Ok(pool_list)) => {
+            let graph = router.graph_mut();
+            let tokens = GraphLoader::load_token_list_file(graph, Path::new(&token_list))?;
+            let pools = GraphLoader::load_pool_list_file(graph, Path::new(&pool_list))?;
+            info!(
+                "📂 已加载 {} 个代币和 {} 条边（跳过 {} 行）",
+                tokens.tokens_loaded,
+                pools.edges_loaded,
+                tokens.errors.len() + pools.errors.len()
+            );
+        }
+        _ => router.initialize(),
+    }
+    // 演示用的模拟报价提供者按已加载的池子构造，接入真实 DEX 时改为注册 HttpQuoteProvider
+    router.register_mock_providers();
+
+    // 长期运行的进程需要定期清理过期的报价和路由，避免缓存只增不减
+    router.quote_service().spawn_cache_sweeper();
+    router.route_cache().spawn_sweeper();
+
+    let refresh_interval = match std::env::var("METIS_REFRESH_INTERVAL_SECS") {
+        Ok(secs) => secs.parse()?,
+        Err(_) => router.config().market_data_refresh_interval_seconds,
+    };
+    let router = Arc::new(router);
+    router.spawn_market_data_refresh(Duration::from_secs(refresh_interval));
+
+    let addr = std::env::var("METIS_SERVER_ADDR")
+        .unwrap_or_else(|_| DEFAULT_ADDR.to_string())
+        .parse()?;
+    metis::server::serve(router, addr).await
+}
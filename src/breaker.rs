@@ -0,0 +1,125 @@
+use dashmap::DashMap;
+use log::{info, warn};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// 按键（如 DEX 平台名称）独立计数的熔断器
+///
+/// 连续失败达到阈值后熔断，冷却期内直接拒绝请求；冷却期结束后放行一个试探请求，
+/// 试探成功则恢复，失败则重新熔断。
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    /// 触发熔断的连续失败次数
+    failure_threshold: u32,
+    /// 熔断后的冷却时间
+    cooldown: Duration,
+    /// 键 -> 熔断状态
+    states: DashMap<String, BreakerState>,
+}
+
+/// 单个键的熔断状态
+#[derive(Debug, Clone, Default)]
+struct BreakerState {
+    /// 连续失败次数
+    consecutive_failures: u32,
+    /// 熔断开始时间，未熔断时为 `None`
+    opened_at: Option<Instant>,
+    /// 半开状态下试探请求的开始时间
+    trial_started_at: Option<Instant>,
+}
+
+/// 熔断器对外暴露的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// 正常放行
+    Closed,
+    /// 熔断中，直接拒绝
+    Open,
+    /// 冷却期已过，等待试探请求的结果
+    HalfOpen,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            states: DashMap::new(),
+        }
+    }
+
+    /// 请求是否可以放行；半开状态下同一时间只放行一个试探请求
+    pub fn try_acquire(&self, key: &str) -> bool {
+        let Some(mut state) = self.states.get_mut(key) else {
+            return true;
+        };
+        let Some(opened_at) = state.opened_at else {
+            return true;
+        };
+        if opened_at.elapsed() < self.cooldown {
+            return false;
+        }
+        // 试探请求被取消时不会回报结果，超过冷却时间后允许新的试探
+        if state
+            .trial_started_at
+            .is_some_and(|started| started.elapsed() < self.cooldown)
+        {
+            return false;
+        }
+        state.trial_started_at = Some(Instant::now());
+        true
+    }
+
+    /// 记录一次成功，重置失败计数并关闭熔断
+    pub fn record_success(&self, key: &str) {
+        if let Some((_, state)) = self.states.remove(key) {
+            if state.opened_at.is_some() {
+                info!("✅ {} 熔断恢复", key);
+            }
+        }
+    }
+
+    /// 记录一次失败，达到阈值或试探失败时熔断
+    pub fn record_failure(&self, key: &str) {
+        let mut state = self.states.entry(key.to_string()).or_default();
+        state.consecutive_failures += 1;
+        let trial_failed = state.trial_started_at.take().is_some();
+        if trial_failed || state.consecutive_failures >= self.failure_threshold {
+            if state.opened_at.is_none() || trial_failed {
+                warn!(
+                    "⛔ {} 连续失败 {} 次，熔断 {} 秒",
+                    key,
+                    state.consecutive_failures,
+                    self.cooldown.as_secs()
+                );
+            }
+            state.opened_at = Some(Instant::now());
+        }
+    }
+
+    /// 键的当前熔断状态
+    pub fn state(&self, key: &str) -> CircuitState {
+        self.states
+            .get(key)
+            .map_or(CircuitState::Closed, |state| self.state_of(&state))
+    }
+
+    /// 当前未处于关闭状态的键及其状态
+    pub fn open_circuits(&self) -> BTreeMap<String, CircuitState> {
+        self.states
+            .iter()
+            .map(|entry| (entry.key().clone(), self.state_of(entry.value())))
+            .filter(|(_, state)| *state != CircuitState::Closed)
+            .collect()
+    }
+
+    fn state_of(&self, state: &BreakerState) -> CircuitState {
+        match state.opened_at {
+            None => CircuitState::Closed,
+            Some(opened_at) if opened_at.elapsed() < self.cooldown => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+}
@@ -0,0 +1,470 @@
+use crate::graph::RoutingGraph;
+use crate::types::*;
+use log::debug;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// 路由结果缓存
+///
+/// 条目按请求建键并记录计算时的图版本。图版本未变时直接命中；图版本变化后，
+/// 只要缓存路由经过的池子和代币解析都没有变化，条目仍然有效，否则视为失效并移除。
+/// 条目数受容量限制，超出时淘汰最久未使用的条目。
+#[derive(Clone)]
+pub struct RouteCache {
+    /// 缓存键 -> 缓存的路由响应
+    entries: LruCache<CachedRoute>,
+}
+
+/// 带图版本的缓存路由
+#[derive(Debug, Clone)]
+struct CachedRoute {
+    /// 缓存的路由响应
+    response: RouteResponse,
+    /// 计算该响应时的图版本号
+    graph_version: u64,
+    /// 响应中各路由经过的池子
+    pools: Vec<String>,
+}
+
+impl RouteCache {
+    pub fn new(capacity: usize, ttl_seconds: u64) -> Self {
+        Self {
+            entries: LruCache::new(capacity, ttl_seconds),
+        }
+    }
+
+    /// 查找仍然有效的缓存响应，过期或失效的条目会被移除
+    pub fn get(&self, key: &str, graph: &RoutingGraph) -> Option<RouteResponse> {
+        let entry = self.entries.get(key)?;
+        let stale = graph.changed_since(
+            entry.graph_version,
+            entry.pools.iter().map(String::as_str),
+        );
+        if !stale {
+            return Some(entry.response);
+        }
+
+        debug!("🗑️  移除失效的路由缓存条目: {}", key);
+        self.entries.remove(key);
+        None
+    }
+
+    /// 缓存在给定图版本上计算出的路由响应，超过容量时淘汰最久未使用的条目
+    pub fn insert(&self, key: String, response: RouteResponse, graph_version: u64) {
+        let pools = Self::route_pools(&response);
+        self.entries.insert(
+            key,
+            CachedRoute {
+                response,
+                graph_version,
+                pools,
+            },
+        );
+    }
+
+    /// 清理过期的缓存条目，返回移除的数量
+    pub fn cleanup_expired(&self) -> usize {
+        self.entries.cleanup_expired()
+    }
+
+    /// 启动按 TTL 周期清理过期条目的后台任务，缓存被释放后任务自动结束
+    ///
+    /// 必须在 tokio 运行时中调用。
+    pub fn spawn_sweeper(&self) -> tokio::task::JoinHandle<()> {
+        self.entries.spawn_sweeper()
+    }
+
+    /// 清空缓存
+    pub fn clear(&self) {
+        self.entries.clear();
+    }
+
+    /// 缓存中的条目数
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 缓存是否为空
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 最大条目数
+    pub fn capacity(&self) -> usize {
+        self.entries.capacity()
+    }
+
+    /// 因容量不足被淘汰的条目数
+    pub fn evictions(&self) -> u64 {
+        self.entries.evictions()
+    }
+
+    /// 响应中最优路由、分割路由和备选路由经过的所有池子
+    fn route_pools(response: &RouteResponse) -> Vec<String> {
+        let split_routes = response.split_route.iter().flat_map(|split| &split.routes);
+        let mut pools: Vec<String> = response
+            .route
+            .iter()
+            .chain(split_routes)
+            .chain(&response.alternatives)
+            .flat_map(|route| &route.segments)
+            .map(|segment| segment.pool_id.clone())
+            .collect();
+        pools.sort();
+        pools.dedup();
+        pools
+    }
+}
+
+/// 容量有限的 LRU 报价缓存
+pub type QuoteCache = LruCache<QuoteResponse>;
+
+/// 容量有限、带生存时间的 LRU 缓存
+///
+/// 条目数超过容量时淘汰最久未使用的条目；过期条目在读取时或由后台清理任务移除。
+pub struct LruCache<V> {
+    /// 受锁保护的条目和使用顺序
+    state: Arc<Mutex<LruState<V>>>,
+    /// 最大条目数
+    capacity: usize,
+    /// 缓存条目的生存时间
+    ttl: Duration,
+    /// 因容量不足被淘汰的条目数
+    evictions: Arc<AtomicU64>,
+}
+
+impl<V> Clone for LruCache<V> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            capacity: self.capacity,
+            ttl: self.ttl,
+            evictions: self.evictions.clone(),
+        }
+    }
+}
+
+/// LRU 缓存的内部状态
+#[derive(Debug)]
+struct LruState<V> {
+    /// 缓存键 -> 缓存条目
+    entries: HashMap<String, LruEntry<V>>,
+    /// 最近使用序号 -> 缓存键，序号最小的条目最久未使用
+    order: BTreeMap<u64, String>,
+    /// 单调递增的使用序号
+    tick: u64,
+}
+
+/// 带过期时间和使用序号的缓存条目
+#[derive(Debug, Clone)]
+struct LruEntry<V> {
+    /// 缓存的值
+    value: V,
+    /// 缓存条目的过期时间
+    expires_at: Instant,
+    /// 最近一次使用的序号
+    last_used: u64,
+}
+
+impl<V> Default for LruState<V> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+}
+
+impl<V> LruState<V> {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn remove(&mut self, key: &str) -> Option<LruEntry<V>> {
+        let entry = self.entries.remove(key)?;
+        self.order.remove(&entry.last_used);
+        Some(entry)
+    }
+
+    /// 只保留满足条件的条目，返回移除的数量
+    fn retain(&mut self, mut keep: impl FnMut(&LruEntry<V>) -> bool) -> usize {
+        let before = self.entries.len();
+        let order = &mut self.order;
+        self.entries.retain(|_, entry| {
+            let kept = keep(entry);
+            if !kept {
+                order.remove(&entry.last_used);
+            }
+            kept
+        });
+        before - self.entries.len()
+    }
+
+    /// 移除所有过期条目，返回移除的数量
+    fn remove_expired(&mut self, now: Instant) -> usize {
+        self.retain(|entry| entry.expires_at > now)
+    }
+}
+
+/// 获取 LRU 状态锁；持锁期间不会 panic，锁中毒时状态仍然一致
+fn lock_state<V>(state: &Mutex<LruState<V>>) -> MutexGuard<'_, LruState<V>> {
+    state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl<V: Clone> LruCache<V> {
+    pub fn new(capacity: usize, ttl_seconds: u64) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(LruState::default())),
+            capacity: capacity.max(1),
+            ttl: Duration::from_secs(ttl_seconds),
+            evictions: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, LruState<V>> {
+        lock_state(&self.state)
+    }
+
+    /// 查找未过期的缓存值并标记为最近使用，过期条目会被移除
+    pub fn get(&self, key: &str) -> Option<V> {
+        let mut state = self.lock();
+        let expires_at = state.entries.get(key)?.expires_at;
+        if expires_at <= Instant::now() {
+            state.remove(key);
+            return None;
+        }
+
+        let tick = state.next_tick();
+        let entry = state.entries.get_mut(key)?;
+        let previous = std::mem::replace(&mut entry.last_used, tick);
+        let value = entry.value.clone();
+        state.order.remove(&previous);
+        state.order.insert(tick, key.to_string());
+        Some(value)
+    }
+
+    /// 缓存值，超过容量时淘汰最久未使用的条目
+    pub fn insert(&self, key: String, value: V) {
+        let mut state = self.lock();
+        state.remove(&key);
+
+        let tick = state.next_tick();
+        state.order.insert(tick, key.clone());
+        state.entries.insert(
+            key,
+            LruEntry {
+                value,
+                expires_at: Instant::now() + self.ttl,
+                last_used: tick,
+            },
+        );
+
+        while state.entries.len() > self.capacity {
+            let Some((_, oldest)) = state.order.pop_first() else {
+                break;
+            };
+            state.entries.remove(&oldest);
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// 移除缓存条目
+    pub fn remove(&self, key: &str) -> Option<V> {
+        self.lock().remove(key).map(|entry| entry.value)
+    }
+
+    /// 只保留满足条件的条目，返回移除的数量
+    pub fn retain(&self, mut keep: impl FnMut(&V) -> bool) -> usize {
+        self.lock().retain(|entry| keep(&entry.value))
+    }
+
+    /// 清理过期的缓存条目，返回移除的数量
+    pub fn cleanup_expired(&self) -> usize {
+        self.lock().remove_expired(Instant::now())
+    }
+
+    /// 清空缓存
+    pub fn clear(&self) {
+        let mut state = self.lock();
+        state.entries.clear();
+        state.order.clear();
+    }
+
+    /// 缓存中的条目数（含尚未清理的过期条目）
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// 缓存是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 已过期但尚未清理的条目数
+    pub fn expired_len(&self) -> usize {
+        let now = Instant::now();
+        self.lock()
+            .entries
+            .values()
+            .filter(|entry| entry.expires_at <= now)
+            .count()
+    }
+
+    /// 最大条目数
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// 因容量不足被淘汰的条目数
+    pub fn evictions(&self) -> u64 {
+        self.evictions.load(Ordering::Relaxed)
+    }
+}
+
+impl<V: Send + 'static> LruCache<V> {
+    /// 启动按 TTL 周期清理过期条目的后台任务，缓存的所有句柄被释放后任务自动结束
+    ///
+    /// 必须在 tokio 运行时中调用。
+    pub fn spawn_sweeper(&self) -> tokio::task::JoinHandle<()> {
+        let state = Arc::downgrade(&self.state);
+        let ttl = self.ttl.max(Duration::from_secs(1));
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(ttl);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let Some(state) = state.upgrade() else {
+                    debug!("🛑 缓存已释放，停止后台清理");
+                    break;
+                };
+                let removed = lock_state(&state).remove_expired(Instant::now());
+                if removed > 0 {
+                    debug!("🧹 后台清理了 {} 个过期缓存条目", removed);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn lru_evicts_least_recently_used() {
+        let cache = LruCache::new(2, 60);
+        cache.insert("a".to_string(), 1);
+        cache.insert("b".to_string(), 2);
+        assert_eq!(cache.get("a"), Some(1));
+
+        cache.insert("c".to_string(), 3);
+
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("a"), Some(1));
+        assert_eq!(cache.get("c"), Some(3));
+        assert_eq!(cache.evictions(), 1);
+
+        // 重新插入已有键不算淘汰，只刷新使用顺序
+        cache.insert("a".to_string(), 10);
+        cache.insert("d".to_string(), 4);
+        assert_eq!(cache.get("c"), None);
+        assert_eq!(cache.get("a"), Some(10));
+        assert_eq!(cache.evictions(), 2);
+    }
+
+    #[test]
+    fn entries_expire_after_ttl() {
+        let cache = LruCache::new(10, 0);
+        cache.insert("a".to_string(), 1);
+        cache.insert("b".to_string(), 2);
+        assert_eq!(cache.expired_len(), 2);
+
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.cleanup_expired(), 1);
+        assert!(cache.is_empty());
+
+        let cache = LruCache::new(10, 60);
+        cache.insert("a".to_string(), 1);
+        assert_eq!(cache.cleanup_expired(), 0);
+        assert_eq!(cache.get("a"), Some(1));
+    }
+
+    /// 在示例图上计算一条 USDC -> SOL 路由，返回缓存后的图、缓存和路由经过的池子
+    async fn cached_route() -> (RoutingGraph, RouteCache, Vec<String>) {
+        let mut graph = RoutingGraph::new(RouterConfig::default());
+        graph.initialize_sample_data();
+        let request = RouteRequest {
+            input_token: "USDC".to_string(),
+            output_token: "SOL".to_string(),
+            input_amount: dec!(1000),
+            swap_mode: SwapMode::ExactIn,
+            output_amount: None,
+            slippage_tolerance: dec!(0.005),
+            max_iterations: 5,
+            max_hops: None,
+            enable_split_routes: false,
+            max_splits: None,
+            max_alternatives: None,
+            filters: RouteFilters::default(),
+        };
+        let search = graph.find_optimal_route(&request).await.unwrap();
+        let response = RouteResponse {
+            request,
+            route: search.route,
+            split_route: None,
+            alternatives: Vec::new(),
+            max_input_amount: None,
+            execution_time_ms: 0,
+            iterations_used: search.iterations,
+        };
+        let pools = RouteCache::route_pools(&response);
+        assert!(!pools.is_empty());
+
+        let cache = RouteCache::new(10, 60);
+        cache.insert("key".to_string(), response, graph.version());
+        (graph, cache, pools)
+    }
+
+    #[tokio::test]
+    async fn route_survives_unrelated_pool_change() {
+        let (mut graph, cache, pools) = cached_route().await;
+        let unrelated = graph
+            .edges
+            .values()
+            .flatten()
+            .map(|edge| edge.pool_id.clone())
+            .find(|pool_id| !pools.contains(pool_id))
+            .expect("示例图中应有路由未经过的池子");
+
+        graph.remove_pool(&unrelated);
+
+        assert!(cache.get("key", &graph).is_some());
+    }
+
+    #[tokio::test]
+    async fn route_invalidated_when_its_pool_changes() {
+        let (mut graph, cache, pools) = cached_route().await;
+
+        graph.remove_pool(&pools[0]);
+
+        assert!(cache.get("key", &graph).is_none());
+        assert!(cache.is_empty());
+    }
+
+    #[tokio::test]
+    async fn route_invalidated_when_tokens_change() {
+        let (mut graph, cache, _) = cached_route().await;
+        let usdc = graph.get_token_by_symbol("USDC").unwrap().address.clone();
+
+        graph.add_token_alias(&usdc, "dollar");
+
+        assert!(cache.get("key", &graph).is_none());
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,615 @@
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+
+/// StableSwap 牛顿迭代的最大次数
+const MAX_NEWTON_ITERATIONS: usize = 255;
+
+/// 牛顿迭代的收敛精度（在归一化空间中）
+const NEWTON_EPSILON: Decimal = dec!(0.000000000000000001);
+
+/// 池子定价曲线
+///
+/// 每条边都有自己方向上的曲线：输入代币为 `from_token`，输出代币为 `to_token`。
+/// 曲线负责给出精确的兑换数量，而不是近似的线性价格影响。
+pub trait PoolCurve {
+    /// 当前边际价格（1 个输入代币可兑换的输出代币数量）
+    fn spot_price(&self) -> Decimal;
+
+    /// 以输入代币计的池子深度
+    fn liquidity(&self) -> Decimal;
+
+    /// 计算给定输入数量的精确输出数量
+    ///
+    /// # 参数
+    /// * `amount_in` - 输入代币数量（不含费用）
+    ///
+    /// # 返回值
+    /// * `Option<Decimal>` - 输出数量；池子无法承接该交易时返回 `None`
+    fn get_amount_out(&self, amount_in: Decimal) -> Option<Decimal>;
+
+    /// 计算得到给定输出数量所需的精确输入数量（`get_amount_out` 的反函数）
+    ///
+    /// # 参数
+    /// * `amount_out` - 期望的输出代币数量
+    ///
+    /// # 返回值
+    /// * `Option<Decimal>` - 所需输入数量；池子无法提供该输出时返回 `None`
+    fn get_amount_in(&self, amount_out: Decimal) -> Option<Decimal>;
+
+    /// 返回成交给定输入数量后的池子状态
+    fn after_swap(&self, amount_in: Decimal) -> Option<Self>
+    where
+        Self: Sized;
+
+    /// 返回反方向（输入与输出代币互换）上的同一池子状态
+    fn reversed(&self) -> Self
+    where
+        Self: Sized;
+
+    /// 计算给定输入数量的价格影响（相对于边际价格的损失比例）
+    fn price_impact(&self, amount_in: Decimal) -> Option<Decimal> {
+        let spot_price = self.spot_price();
+        if amount_in <= dec!(0) || spot_price <= dec!(0) {
+            return Some(dec!(0));
+        }
+        let amount_out = self.get_amount_out(amount_in)?;
+        let execution_price = amount_out / amount_in;
+        Some((dec!(1) - execution_price / spot_price).max(dec!(0)))
+    }
+}
+
+/// 恒定乘积曲线（x * y = k），如 Raydium AMM
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConstantProductCurve {
+    /// 输入代币储备量
+    pub reserve_in: Decimal,
+    /// 输出代币储备量
+    pub reserve_out: Decimal,
+}
+
+impl ConstantProductCurve {
+    pub fn new(reserve_in: Decimal, reserve_out: Decimal) -> Self {
+        Self {
+            reserve_in,
+            reserve_out,
+        }
+    }
+
+    /// 由输入侧深度和边际价格构造曲线
+    pub fn from_rate(liquidity: Decimal, exchange_rate: Decimal) -> Self {
+        Self::new(liquidity, liquidity * exchange_rate)
+    }
+}
+
+impl PoolCurve for ConstantProductCurve {
+    fn spot_price(&self) -> Decimal {
+        if self.reserve_in <= dec!(0) {
+            return dec!(0);
+        }
+        self.reserve_out / self.reserve_in
+    }
+
+    fn liquidity(&self) -> Decimal {
+        self.reserve_in
+    }
+
+    fn get_amount_out(&self, amount_in: Decimal) -> Option<Decimal> {
+        if amount_in < dec!(0) || self.reserve_in <= dec!(0) || self.reserve_out <= dec!(0) {
+            return None;
+        }
+        // dy = y * dx / (x + dx)
+        let denominator = self.reserve_in.checked_add(amount_in)?;
+        self.reserve_out.checked_mul(amount_in)?.checked_div(denominator)
+    }
+
+    fn get_amount_in(&self, amount_out: Decimal) -> Option<Decimal> {
+        if amount_out < dec!(0) || amount_out >= self.reserve_out || self.reserve_in <= dec!(0) {
+            return None;
+        }
+        // dx = x * dy / (y - dy)
+        self.reserve_in
+            .checked_mul(amount_out)?
+            .checked_div(self.reserve_out - amount_out)
+    }
+
+    fn after_swap(&self, amount_in: Decimal) -> Option<Self> {
+        let amount_out = self.get_amount_out(amount_in)?;
+        Some(Self::new(self.reserve_in + amount_in, self.reserve_out - amount_out))
+    }
+
+    fn reversed(&self) -> Self {
+        Self::new(self.reserve_out, self.reserve_in)
+    }
+}
+
+/// StableSwap 曲线（Curve 风格的两币不变量），用于锚定资产之间的池子
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StableSwapCurve {
+    /// 输入代币储备量
+    pub reserve_in: Decimal,
+    /// 输出代币储备量
+    pub reserve_out: Decimal,
+    /// 放大系数 A，越大越接近恒定和
+    pub amplification: Decimal,
+}
+
+impl StableSwapCurve {
+    pub fn new(reserve_in: Decimal, reserve_out: Decimal, amplification: Decimal) -> Self {
+        Self {
+            reserve_in,
+            reserve_out,
+            amplification,
+        }
+    }
+
+    /// 归一化的不变量 D（储备量已除以两者之和）
+    fn invariant(&self, x: Decimal, y: Decimal) -> Option<Decimal> {
+        let sum = x + y;
+        if sum <= dec!(0) {
+            return Some(dec!(0));
+        }
+        let ann = self.amplification * dec!(4);
+        let mut d = sum;
+        for _ in 0..MAX_NEWTON_ITERATIONS {
+            let d_p = d.checked_mul(d)?.checked_div(x * dec!(2))?;
+            let d_p = d_p.checked_mul(d)?.checked_div(y * dec!(2))?;
+            let previous = d;
+            let numerator = (ann * sum + d_p * dec!(2)).checked_mul(d)?;
+            let denominator = (ann - dec!(1)) * d + dec!(3) * d_p;
+            d = numerator.checked_div(denominator)?;
+            if (d - previous).abs() <= NEWTON_EPSILON {
+                return Some(d);
+            }
+        }
+        Some(d)
+    }
+
+    /// 给定一侧的新储备量和不变量 D，求另一侧的储备量
+    fn solve_other_reserve(&self, x: Decimal, d: Decimal) -> Option<Decimal> {
+        if x <= dec!(0) {
+            return None;
+        }
+        let ann = self.amplification * dec!(4);
+        // c = D^3 / (4 * x * Ann), b = x + D / Ann
+        let c = d.checked_mul(d)?.checked_div(x * dec!(2))?;
+        let c = c.checked_mul(d)?.checked_div(ann * dec!(2))?;
+        let b = x + d.checked_div(ann)?;
+        let mut y = d;
+        for _ in 0..MAX_NEWTON_ITERATIONS {
+            let previous = y;
+            y = (y.checked_mul(y)? + c).checked_div(y * dec!(2) + b - d)?;
+            if (y - previous).abs() <= NEWTON_EPSILON {
+                return Some(y);
+            }
+        }
+        Some(y)
+    }
+}
+
+impl PoolCurve for StableSwapCurve {
+    fn spot_price(&self) -> Decimal {
+        // 用极小数量的兑换近似边际价格
+        let probe = self.reserve_in * dec!(0.000001);
+        if probe <= dec!(0) {
+            return dec!(0);
+        }
+        self.get_amount_out(probe)
+            .map(|amount_out| amount_out / probe)
+            .unwrap_or(dec!(0))
+    }
+
+    fn liquidity(&self) -> Decimal {
+        self.reserve_in
+    }
+
+    fn get_amount_out(&self, amount_in: Decimal) -> Option<Decimal> {
+        if amount_in < dec!(0) || self.reserve_in <= dec!(0) || self.reserve_out <= dec!(0) {
+            return None;
+        }
+        // 在归一化空间中迭代以避免大储备量时的溢出
+        let scale = self.reserve_in + self.reserve_out;
+        let x = self.reserve_in / scale;
+        let y = self.reserve_out / scale;
+        let d = self.invariant(x, y)?;
+        let new_x = x + amount_in.checked_div(scale)?;
+        let new_y = self.solve_other_reserve(new_x, d)?;
+        let amount_out = (y - new_y).checked_mul(scale)?;
+        Some(amount_out.max(dec!(0)).min(self.reserve_out))
+    }
+
+    fn get_amount_in(&self, amount_out: Decimal) -> Option<Decimal> {
+        if amount_out < dec!(0) || amount_out >= self.reserve_out || self.reserve_in <= dec!(0) {
+            return None;
+        }
+        // 不变量对两侧对称，反向求解输入侧的新储备量
+        let scale = self.reserve_in + self.reserve_out;
+        let x = self.reserve_in / scale;
+        let y = self.reserve_out / scale;
+        let d = self.invariant(x, y)?;
+        let new_y = y - amount_out.checked_div(scale)?;
+        let new_x = self.solve_other_reserve(new_y, d)?;
+        let amount_in = (new_x - x).checked_mul(scale)?;
+        Some(amount_in.max(dec!(0)))
+    }
+
+    fn after_swap(&self, amount_in: Decimal) -> Option<Self> {
+        let amount_out = self.get_amount_out(amount_in)?;
+        Some(Self::new(
+            self.reserve_in + amount_in,
+            self.reserve_out - amount_out,
+            self.amplification,
+        ))
+    }
+
+    fn reversed(&self) -> Self {
+        Self::new(self.reserve_out, self.reserve_in, self.amplification)
+    }
+}
+
+/// 集中流动性曲线（单个活跃区间，Uniswap v3 / Orca Whirlpool 风格）
+///
+/// 价格以输出代币/输入代币表示；卖出输入代币会使价格向 `sqrt_price_lower` 移动。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConcentratedLiquidityCurve {
+    /// 活跃区间内的流动性 L
+    pub liquidity: Decimal,
+    /// 当前价格的平方根
+    pub sqrt_price: Decimal,
+    /// 区间下界价格的平方根
+    pub sqrt_price_lower: Decimal,
+    /// 区间上界价格的平方根
+    pub sqrt_price_upper: Decimal,
+}
+
+impl ConcentratedLiquidityCurve {
+    pub fn new(
+        liquidity: Decimal,
+        sqrt_price: Decimal,
+        sqrt_price_lower: Decimal,
+        sqrt_price_upper: Decimal,
+    ) -> Self {
+        Self {
+            liquidity,
+            sqrt_price,
+            sqrt_price_lower,
+            sqrt_price_upper,
+        }
+    }
+
+    /// 成交给定输入数量后的价格平方根；超出活跃区间时返回 `None`
+    fn sqrt_price_after(&self, amount_in: Decimal) -> Option<Decimal> {
+        if amount_in < dec!(0) || self.liquidity <= dec!(0) || self.sqrt_price <= dec!(0) {
+            return None;
+        }
+        // sqrt(P') = L * sqrt(P) / (L + dx * sqrt(P))
+        let denominator = self.liquidity + amount_in.checked_mul(self.sqrt_price)?;
+        let new_sqrt_price = self
+            .liquidity
+            .checked_mul(self.sqrt_price)?
+            .checked_div(denominator)?;
+        if new_sqrt_price < self.sqrt_price_lower {
+            // 超出活跃区间，区间内流动性不足以承接该交易
+            return None;
+        }
+        Some(new_sqrt_price)
+    }
+
+    /// 由输入侧虚拟深度、当前价格和价格区间构造曲线
+    pub fn from_rate(
+        liquidity: Decimal,
+        exchange_rate: Decimal,
+        price_lower: Decimal,
+        price_upper: Decimal,
+    ) -> Self {
+        let sqrt_price = decimal_sqrt(exchange_rate);
+        Self::new(
+            liquidity * sqrt_price,
+            sqrt_price,
+            decimal_sqrt(price_lower),
+            decimal_sqrt(price_upper),
+        )
+    }
+}
+
+impl PoolCurve for ConcentratedLiquidityCurve {
+    fn spot_price(&self) -> Decimal {
+        self.sqrt_price * self.sqrt_price
+    }
+
+    fn liquidity(&self) -> Decimal {
+        if self.sqrt_price <= dec!(0) {
+            return dec!(0);
+        }
+        // 虚拟输入储备量 x = L / sqrt(P)
+        self.liquidity / self.sqrt_price
+    }
+
+    fn get_amount_out(&self, amount_in: Decimal) -> Option<Decimal> {
+        let new_sqrt_price = self.sqrt_price_after(amount_in)?;
+        // dy = L * (sqrt(P) - sqrt(P'))
+        self.liquidity.checked_mul(self.sqrt_price - new_sqrt_price)
+    }
+
+    fn get_amount_in(&self, amount_out: Decimal) -> Option<Decimal> {
+        if amount_out < dec!(0) || self.liquidity <= dec!(0) || self.sqrt_price <= dec!(0) {
+            return None;
+        }
+        // sqrt(P') = sqrt(P) - dy / L
+        let new_sqrt_price = self.sqrt_price - amount_out.checked_div(self.liquidity)?;
+        if new_sqrt_price <= dec!(0) || new_sqrt_price < self.sqrt_price_lower {
+            return None;
+        }
+        // dx = L * (sqrt(P) - sqrt(P')) / (sqrt(P) * sqrt(P'))
+        self.liquidity
+            .checked_mul(self.sqrt_price - new_sqrt_price)?
+            .checked_div(self.sqrt_price.checked_mul(new_sqrt_price)?)
+    }
+
+    fn after_swap(&self, amount_in: Decimal) -> Option<Self> {
+        let mut next = self.clone();
+        next.sqrt_price = self.sqrt_price_after(amount_in)?;
+        Some(next)
+    }
+
+    fn reversed(&self) -> Self {
+        // 反方向的价格为倒数，区间上下界随之互换
+        Self::new(
+            self.liquidity,
+            invert(self.sqrt_price),
+            invert(self.sqrt_price_upper),
+            invert(self.sqrt_price_lower),
+        )
+    }
+}
+
+/// 附加在边上的曲线模型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Curve {
+    ConstantProduct(ConstantProductCurve),
+    StableSwap(StableSwapCurve),
+    ConcentratedLiquidity(ConcentratedLiquidityCurve),
+}
+
+impl Curve {
+    /// 用新的边际价格和输入侧深度重建同类型的曲线
+    ///
+    /// StableSwap 曲线保留放大系数和储备比例，只按新深度缩放储备；
+    /// 集中流动性曲线保留价格区间。
+    pub fn with_market_data(&self, exchange_rate: Decimal, liquidity: Decimal) -> Self {
+        match self {
+            Curve::ConstantProduct(_) => {
+                Curve::ConstantProduct(ConstantProductCurve::from_rate(liquidity, exchange_rate))
+            }
+            Curve::StableSwap(curve) => {
+                // 稳定池的边际价格在锚定附近几乎不随储备比例变化，无法从汇率反推储备，
+                // 直接用汇率重建会把失衡的池子变成平衡池子
+                let reserve_out = if curve.reserve_in > dec!(0) {
+                    liquidity / curve.reserve_in * curve.reserve_out
+                } else {
+                    liquidity * exchange_rate
+                };
+                Curve::StableSwap(StableSwapCurve::new(liquidity, reserve_out, curve.amplification))
+            }
+            Curve::ConcentratedLiquidity(curve) => {
+                Curve::ConcentratedLiquidity(ConcentratedLiquidityCurve::from_rate(
+                    liquidity,
+                    exchange_rate,
+                    curve.sqrt_price_lower * curve.sqrt_price_lower,
+                    curve.sqrt_price_upper * curve.sqrt_price_upper,
+                ))
+            }
+        }
+    }
+
+    fn as_pool_curve(&self) -> &dyn PoolCurve {
+        match self {
+            Curve::ConstantProduct(curve) => curve,
+            Curve::StableSwap(curve) => curve,
+            Curve::ConcentratedLiquidity(curve) => curve,
+        }
+    }
+}
+
+impl PoolCurve for Curve {
+    fn spot_price(&self) -> Decimal {
+        self.as_pool_curve().spot_price()
+    }
+
+    fn liquidity(&self) -> Decimal {
+        self.as_pool_curve().liquidity()
+    }
+
+    fn get_amount_out(&self, amount_in: Decimal) -> Option<Decimal> {
+        self.as_pool_curve().get_amount_out(amount_in)
+    }
+
+    fn get_amount_in(&self, amount_out: Decimal) -> Option<Decimal> {
+        self.as_pool_curve().get_amount_in(amount_out)
+    }
+
+    fn after_swap(&self, amount_in: Decimal) -> Option<Self> {
+        Some(match self {
+            Curve::ConstantProduct(curve) => Curve::ConstantProduct(curve.after_swap(amount_in)?),
+            Curve::StableSwap(curve) => Curve::StableSwap(curve.after_swap(amount_in)?),
+            Curve::ConcentratedLiquidity(curve) => {
+                Curve::ConcentratedLiquidity(curve.after_swap(amount_in)?)
+            }
+        })
+    }
+
+    fn reversed(&self) -> Self {
+        match self {
+            Curve::ConstantProduct(curve) => Curve::ConstantProduct(curve.reversed()),
+            Curve::StableSwap(curve) => Curve::StableSwap(curve.reversed()),
+            Curve::ConcentratedLiquidity(curve) => Curve::ConcentratedLiquidity(curve.reversed()),
+        }
+    }
+}
+
+/// 通过 f64 计算小数的平方根
+fn decimal_sqrt(value: Decimal) -> Decimal {
+    value
+        .to_f64()
+        .and_then(|v| Decimal::from_f64(v.sqrt()))
+        .unwrap_or(dec!(0))
+}
+
+/// 小数的倒数，零值返回零
+fn invert(value: Decimal) -> Decimal {
+    if value.is_zero() {
+        return dec!(0);
+    }
+    dec!(1) / value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 两个数量的相对误差是否在容差内
+    fn approx_eq(a: Decimal, b: Decimal, tolerance: Decimal) -> bool {
+        if b.is_zero() {
+            return a.abs() <= tolerance;
+        }
+        ((a - b) / b).abs() <= tolerance
+    }
+
+    fn assert_round_trip(curve: &Curve, amounts: &[Decimal]) {
+        for &amount_in in amounts {
+            let amount_out = curve.get_amount_out(amount_in).expect("输出数量");
+            let recovered = curve.get_amount_in(amount_out).expect("输入数量");
+            assert!(
+                approx_eq(recovered, amount_in, dec!(0.000001)),
+                "{:?}: 输入 {} -> 输出 {} -> 反算输入 {}",
+                curve,
+                amount_in,
+                amount_out,
+                recovered
+            );
+        }
+    }
+
+    #[test]
+    fn constant_product_round_trip() {
+        let curve = Curve::ConstantProduct(ConstantProductCurve::new(dec!(1000000), dec!(1000)));
+        assert_round_trip(&curve, &[dec!(1), dec!(100), dec!(10000), dec!(500000)]);
+    }
+
+    #[test]
+    fn constant_product_spot_price_matches_reserves() {
+        let curve = ConstantProductCurve::new(dec!(2000), dec!(50));
+        assert_eq!(curve.spot_price(), dec!(0.025));
+        assert_eq!(curve.liquidity(), dec!(2000));
+        assert_eq!(ConstantProductCurve::from_rate(dec!(2000), dec!(0.025)), curve);
+        assert_eq!(curve.reversed().spot_price(), dec!(40));
+    }
+
+    #[test]
+    fn stable_swap_round_trip() {
+        let balanced =
+            Curve::StableSwap(StableSwapCurve::new(dec!(1000000), dec!(1000000), dec!(100)));
+        assert_round_trip(&balanced, &[dec!(1), dec!(1000), dec!(100000), dec!(900000)]);
+
+        let imbalanced =
+            Curve::StableSwap(StableSwapCurve::new(dec!(300000), dec!(1700000), dec!(50)));
+        assert_round_trip(&imbalanced, &[dec!(1), dec!(1000), dec!(100000)]);
+    }
+
+    #[test]
+    fn stable_swap_spot_price_near_peg() {
+        let balanced = StableSwapCurve::new(dec!(1000000), dec!(1000000), dec!(100));
+        assert!(approx_eq(balanced.spot_price(), dec!(1), dec!(0.0001)));
+
+        // 输出侧储备更多时输入代币更值钱，边际价格高于 1，但远小于储备比例
+        let imbalanced = StableSwapCurve::new(dec!(300000), dec!(1700000), dec!(50));
+        let spot = imbalanced.spot_price();
+        assert!(spot > dec!(1) && spot < dec!(1.1), "spot = {}", spot);
+        assert!(approx_eq(
+            imbalanced.reversed().spot_price() * spot,
+            dec!(1),
+            dec!(0.0001)
+        ));
+    }
+
+    #[test]
+    fn stable_swap_market_data_keeps_reserve_ratio() {
+        let imbalanced =
+            Curve::StableSwap(StableSwapCurve::new(dec!(300000), dec!(1700000), dec!(50)));
+        let refreshed = imbalanced.with_market_data(imbalanced.spot_price(), dec!(600000));
+        assert_eq!(
+            refreshed,
+            Curve::StableSwap(StableSwapCurve::new(dec!(600000), dec!(3400000), dec!(50)))
+        );
+    }
+
+    #[test]
+    fn stable_swap_zero_amplification_does_not_panic() {
+        let curve = StableSwapCurve::new(dec!(1000), dec!(1000), dec!(0));
+        assert_eq!(curve.get_amount_out(dec!(10)), None);
+        assert_eq!(curve.get_amount_in(dec!(10)), None);
+        assert_eq!(curve.spot_price(), dec!(0));
+    }
+
+    #[test]
+    fn concentrated_liquidity_round_trip() {
+        let curve = Curve::ConcentratedLiquidity(ConcentratedLiquidityCurve::from_rate(
+            dec!(1000000),
+            dec!(0.001),
+            dec!(0.0008),
+            dec!(0.0012),
+        ));
+        assert_round_trip(&curve, &[dec!(1), dec!(1000), dec!(50000)]);
+    }
+
+    #[test]
+    fn concentrated_liquidity_rejects_trades_beyond_range() {
+        let curve = ConcentratedLiquidityCurve::from_rate(
+            dec!(1000000),
+            dec!(0.001),
+            dec!(0.0008),
+            dec!(0.0012),
+        );
+        assert!(approx_eq(curve.spot_price(), dec!(0.001), dec!(0.000001)));
+        assert!(approx_eq(curve.liquidity(), dec!(1000000), dec!(0.000001)));
+        assert_eq!(curve.get_amount_out(dec!(100000000)), None);
+    }
+
+    #[test]
+    fn concentrated_liquidity_reversed_inverts_price_and_range() {
+        let curve = ConcentratedLiquidityCurve::from_rate(
+            dec!(1000000),
+            dec!(0.001),
+            dec!(0.0008),
+            dec!(0.0012),
+        );
+        let reversed = curve.reversed();
+        assert!(approx_eq(reversed.spot_price(), dec!(1000), dec!(0.000001)));
+        assert!(reversed.sqrt_price_lower < reversed.sqrt_price);
+        assert!(reversed.sqrt_price < reversed.sqrt_price_upper);
+        assert_round_trip(
+            &Curve::ConcentratedLiquidity(reversed),
+            &[dec!(0.001), dec!(1), dec!(10)],
+        );
+    }
+
+    #[test]
+    fn after_swap_moves_price_against_the_trader() {
+        let curves = [
+            Curve::ConstantProduct(ConstantProductCurve::new(dec!(1000000), dec!(1000))),
+            Curve::StableSwap(StableSwapCurve::new(dec!(1000000), dec!(1000000), dec!(100))),
+            Curve::ConcentratedLiquidity(ConcentratedLiquidityCurve::from_rate(
+                dec!(1000000),
+                dec!(0.001),
+                dec!(0.0008),
+                dec!(0.0012),
+            )),
+        ];
+        for curve in curves {
+            let after = curve.after_swap(dec!(10000)).expect("成交后的池子");
+            assert!(after.spot_price() < curve.spot_price(), "{:?}", curve);
+        }
+    }
+}
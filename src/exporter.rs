@@ -0,0 +1,215 @@
+use crate::metrics::HistogramSnapshot;
+use crate::quote::CacheStats;
+use crate::routing::{MetisRouter, RoutingStats};
+use std::fmt::Write;
+
+/// Prometheus 文本格式的内容类型
+pub const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// 将路由器的当前指标编码为 Prometheus 文本格式
+pub fn render_metrics(router: &MetisRouter) -> String {
+    encode_metrics(&router.get_routing_stats(), &router.get_quote_stats())
+}
+
+/// 将路由统计和报价统计编码为 Prometheus 文本格式
+pub fn encode_metrics(routing: &RoutingStats, quotes: &CacheStats) -> String {
+    let mut out = String::new();
+
+    write_gauge(&mut out, "metis_graph_nodes", "路由图中的代币数量", routing.total_nodes as f64);
+    write_gauge(&mut out, "metis_graph_edges", "路由图中的边数量", routing.total_edges as f64);
+    write_gauge(&mut out, "metis_graph_version", "路由图的当前版本号", routing.graph_version as f64);
+    write_gauge(
+        &mut out,
+        "metis_graph_stale_edges",
+        "最近一次市场数据刷新失败的边数",
+        routing.stale_edges as f64,
+    );
+
+    write_counter(&mut out, "metis_route_requests_total", "路由请求总数", routing.total_requests);
+    write_counter(
+        &mut out,
+        "metis_route_success_total",
+        "找到路由的请求数",
+        routing.successful_requests,
+    );
+    write_labeled_counter(
+        &mut out,
+        "metis_route_no_route_total",
+        "按原因统计的未返回路由次数",
+        "reason",
+        &routing.no_route_by_reason,
+    );
+
+    // 延迟按 Prometheus 约定以秒为单位导出
+    write_histogram(
+        &mut out,
+        "metis_route_latency_seconds",
+        "路由请求延迟（秒）",
+        &routing.latency,
+        0.001,
+    );
+    write_histogram(
+        &mut out,
+        "metis_route_iterations",
+        "每次路由搜索使用的 Bellman-Ford 迭代次数",
+        &routing.iterations,
+        1.0,
+    );
+
+    write_counter(&mut out, "metis_route_cache_hits_total", "路由缓存命中次数", routing.cache_hits);
+    write_counter(
+        &mut out,
+        "metis_route_cache_misses_total",
+        "路由缓存未命中次数",
+        routing.cache_misses,
+    );
+    write_gauge(&mut out, "metis_route_cache_hit_ratio", "路由缓存命中率", routing.cache_hit_rate);
+    write_gauge(
+        &mut out,
+        "metis_route_cache_entries",
+        "路由缓存中的条目数",
+        routing.cache_entries as f64,
+    );
+    write_counter(
+        &mut out,
+        "metis_route_cache_evictions_total",
+        "因容量不足被淘汰的路由缓存条目数",
+        routing.cache_evictions,
+    );
+
+    write_counter(&mut out, "metis_quote_cache_hits_total", "报价缓存命中次数", quotes.cache_hits);
+    write_counter(
+        &mut out,
+        "metis_quote_cache_misses_total",
+        "报价缓存未命中次数",
+        quotes.cache_misses,
+    );
+    write_gauge(&mut out, "metis_quote_cache_hit_ratio", "报价缓存命中率", quotes.cache_hit_rate);
+    write_gauge(
+        &mut out,
+        "metis_quote_cache_entries",
+        "报价缓存中的条目数",
+        quotes.total_entries as f64,
+    );
+    write_counter(
+        &mut out,
+        "metis_quote_cache_evictions_total",
+        "因容量不足被淘汰的报价缓存条目数",
+        quotes.evictions,
+    );
+    write_counter(
+        &mut out,
+        "metis_quote_coalesced_total",
+        "合并到进行中报价请求的等待次数",
+        quotes.coalesced_requests,
+    );
+    write_gauge(
+        &mut out,
+        "metis_quote_in_flight",
+        "进行中的报价请求数",
+        quotes.in_flight_requests as f64,
+    );
+    write_labeled_counter(
+        &mut out,
+        "metis_quote_errors_total",
+        "按 DEX 平台统计的报价失败次数",
+        "dex_platform",
+        &quotes.errors_by_dex,
+    );
+
+    out
+}
+
+fn write_header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn write_gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    write_header(out, name, help, "gauge");
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn write_counter(out: &mut String, name: &str, help: &str, value: u64) {
+    write_header(out, name, help, "counter");
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn write_labeled_counter<'a>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    label: &str,
+    values: impl IntoIterator<Item = (&'a String, &'a u64)>,
+) {
+    write_header(out, name, help, "counter");
+    for (label_value, value) in values {
+        let _ = writeln!(
+            out,
+            "{}{{{}=\"{}\"}} {}",
+            name,
+            label,
+            escape_label_value(label_value),
+            value
+        );
+    }
+}
+
+/// 写出直方图，`scale` 用于把快照中的单位换算为导出单位
+fn write_histogram(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    snapshot: &HistogramSnapshot,
+    scale: f64,
+) {
+    write_header(out, name, help, "histogram");
+    for (bound, count) in &snapshot.buckets {
+        let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound * scale, count);
+    }
+    let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, snapshot.count);
+    let _ = writeln!(out, "{}_sum {}", name, snapshot.sum * scale);
+    let _ = writeln!(out, "{}_count {}", name, snapshot.count);
+}
+
+/// 按 Prometheus 文本格式转义标签值
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// 在指定地址上提供 `GET /metrics` 端点，直到服务器出错为止
+#[cfg(feature = "metrics-exporter")]
+pub async fn serve_metrics(
+    router: std::sync::Arc<MetisRouter>,
+    addr: std::net::SocketAddr,
+) -> anyhow::Result<()> {
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{header, Body, Method, Request, Response, Server, StatusCode};
+    use std::convert::Infallible;
+
+    let make_service = make_service_fn(move |_| {
+        let router = router.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let router = router.clone();
+                async move {
+                    match (request.method(), request.uri().path()) {
+                        (&Method::GET, "/metrics") => Response::builder()
+                            .header(header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)
+                            .body(Body::from(render_metrics(&router))),
+                        _ => Response::builder()
+                            .status(StatusCode::NOT_FOUND)
+                            .body(Body::empty()),
+                    }
+                }
+            }))
+        }
+    });
+
+    log::info!("📈 Prometheus 指标端点监听于 http://{}/metrics", addr);
+    Server::try_bind(&addr)?.serve(make_service).await?;
+    Ok(())
+}
@@ -1,18 +1,30 @@
+use crate::curve::{ConcentratedLiquidityCurve, ConstantProductCurve, Curve, PoolCurve};
+use crate::registry::{TokenLookupError, TokenRegistry};
 use crate::types::*;
+use crate::utils::MathUtils;
 use anyhow::Result;
-use dashmap::DashMap;
 use log::{debug, info, warn};
-use rust_decimal::Decimal;
+use rust_decimal::{Decimal, RoundingStrategy};
 use rust_decimal_macros::dec;
-use std::collections::{HashMap, HashSet, VecDeque};
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
 
 /// Metis 路由算法的图表示
+#[derive(Clone)]
 pub struct RoutingGraph {
     pub nodes: HashMap<String, Token>,
     pub edges: HashMap<String, Vec<Edge>>, // token_address -> edges
     pub config: RouterConfig,
-    pub quote_cache: Arc<DashMap<String, QuoteResponse>>,
+    /// 按 mint 地址、符号和别名索引的代币注册表
+    registry: TokenRegistry,
+    /// 图版本号，每次代币或边发生变化时递增
+    version: u64,
+    /// 每个池子最近一次发生变化（更新或移除）时的图版本号
+    pool_versions: HashMap<String, u64>,
+    /// 代币或代币注册表最近一次发生变化时的图版本号
+    token_version: u64,
+    /// 1 个原生代币可折算的各代币数量，按图版本缓存，图发生变化时清空
+    gas_rates: OnceLock<HashMap<String, Decimal>>,
 }
 
 impl RoutingGraph {
@@ -21,22 +33,250 @@ impl RoutingGraph {
             nodes: HashMap::new(),
             edges: HashMap::new(),
             config,
-            quote_cache: Arc::new(DashMap::new()),
+            registry: TokenRegistry::new(),
+            version: 0,
+            pool_versions: HashMap::new(),
+            token_version: 0,
+            gas_rates: OnceLock::new(),
         }
     }
 
+    /// 当前图版本号
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// 自给定图版本以来，列出的池子或代币解析是否发生过变化
+    pub fn changed_since<'a>(
+        &self,
+        version: u64,
+        pool_ids: impl IntoIterator<Item = &'a str>,
+    ) -> bool {
+        if self.version == version {
+            return false;
+        }
+        self.token_version > version
+            || pool_ids.into_iter().any(|pool_id| {
+                self.pool_versions
+                    .get(pool_id)
+                    .is_some_and(|changed| *changed > version)
+            })
+    }
+
+    /// 递增图版本号并记录发生变化的池子
+    fn bump_version<'a>(&mut self, changed_pools: impl IntoIterator<Item = &'a str>) {
+        self.version += 1;
+        self.gas_rates = OnceLock::new();
+        for pool_id in changed_pools {
+            self.pool_versions.insert(pool_id.to_string(), self.version);
+        }
+    }
+
+    /// 递增图版本号并记录代币发生变化
+    fn bump_token_version(&mut self) {
+        self.version += 1;
+        self.gas_rates = OnceLock::new();
+        self.token_version = self.version;
+    }
+
     /// 向图中添加代币
     pub fn add_token(&mut self, token: Token) {
-        self.nodes.insert(token.address.clone(), token);
+        self.upsert_token(token);
     }
 
-    /// 向图中添加边（交易对）
+    /// 向图中添加边（交易对），同一池子同一方向的已有边会被替换
     pub fn add_edge(&mut self, edge: Edge) {
-        let from_addr = edge.from_token.address.clone();
+        self.upsert_edge(edge);
+    }
+
+    /// 插入或更新代币，并同步更新引用该代币的边
+    ///
+    /// 返回该代币是否为新加入的代币。
+    pub fn upsert_token(&mut self, token: Token) -> bool {
+        if self.nodes.get(&token.address) == Some(&token) {
+            return false;
+        }
+
+        for edge in self.edges.values_mut().flatten() {
+            if edge.from_token.address == token.address {
+                edge.from_token = token.clone();
+            }
+            if edge.to_token.address == token.address {
+                edge.to_token = token.clone();
+            }
+        }
+
+        self.registry.upsert(token.clone());
+        let is_new = self.nodes.insert(token.address.clone(), token).is_none();
+        self.bump_token_version();
+        is_new
+    }
+
+    /// 设置代币的验证标记，代币不存在时返回 `false`
+    pub fn set_token_verified(&mut self, address: &str, verified: bool) -> bool {
+        let updated = self.registry.set_verified(address, verified);
+        if updated {
+            self.bump_token_version();
+        }
+        updated
+    }
+
+    /// 为代币添加查找别名，代币不存在时返回 `false`
+    pub fn add_token_alias(&mut self, address: &str, alias: &str) -> bool {
+        let updated = self.registry.add_alias(address, alias);
+        if updated {
+            self.bump_token_version();
+        }
+        updated
+    }
+
+    /// 代币注册表
+    pub fn token_registry(&self) -> &TokenRegistry {
+        &self.registry
+    }
+
+    /// 移除代币及所有以其为输入或输出的边
+    pub fn remove_token(&mut self, address: &str) -> Option<Token> {
+        let token = self.nodes.remove(address)?;
+        self.registry.remove(address);
+
+        let mut removed_pools: Vec<String> = self
+            .edges
+            .remove(address)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|edge| edge.pool_id)
+            .collect();
+        for edges in self.edges.values_mut() {
+            edges.retain(|edge| {
+                let keep = edge.to_token.address != address;
+                if !keep {
+                    removed_pools.push(edge.pool_id.clone());
+                }
+                keep
+            });
+        }
+        self.edges.retain(|_, edges| !edges.is_empty());
+
+        self.bump_version(removed_pools.iter().map(String::as_str));
+        self.token_version = self.version;
+        Some(token)
+    }
+
+    /// 按池子和方向插入或替换边，并由曲线重新计算汇率、流动性和权重
+    ///
+    /// 边两端的代币不在图中时一并加入。返回被替换的旧边。
+    pub fn upsert_edge(&mut self, mut edge: Edge) -> Option<Edge> {
+        edge.recompute();
+
+        let mut new_tokens = false;
+        for token in [&edge.from_token, &edge.to_token] {
+            if !self.nodes.contains_key(&token.address) {
+                self.registry.upsert(token.clone());
+                self.nodes.insert(token.address.clone(), token.clone());
+                new_tokens = true;
+            }
+        }
+        // 新代币可能使已有符号变得不唯一，按符号缓存的路由需要失效
+        if new_tokens {
+            self.bump_token_version();
+        }
+
+        let pool_id = edge.pool_id.clone();
+        let edges = self.edges.entry(edge.from_token.address.clone()).or_default();
+        let previous = match edges.iter_mut().find(|existing| existing.pool_id == edge.pool_id) {
+            Some(existing) => Some(std::mem::replace(existing, edge)),
+            None => {
+                edges.push(edge);
+                None
+            }
+        };
+
+        self.bump_version([pool_id.as_str()]);
+        previous
+    }
+
+    /// 移除某个池子在给定输入代币方向上的边
+    pub fn remove_edge(&mut self, pool_id: &str, from_addr: &str) -> Option<Edge> {
+        let edges = self.edges.get_mut(from_addr)?;
+        let index = edges.iter().position(|edge| edge.pool_id == pool_id)?;
+        let removed = edges.remove(index);
+        if edges.is_empty() {
+            self.edges.remove(from_addr);
+        }
+
+        self.bump_version([pool_id]);
+        Some(removed)
+    }
+
+    /// 移除某个池子在两个方向上的所有边
+    pub fn remove_pool(&mut self, pool_id: &str) -> Vec<Edge> {
+        let mut removed = Vec::new();
+        for edges in self.edges.values_mut() {
+            let (pool_edges, rest): (Vec<Edge>, Vec<Edge>) =
+                edges.drain(..).partition(|edge| edge.pool_id == pool_id);
+            *edges = rest;
+            removed.extend(pool_edges);
+        }
+        self.edges.retain(|_, edges| !edges.is_empty());
+
+        if !removed.is_empty() {
+            self.bump_version([pool_id]);
+        }
+        removed
+    }
+
+    /// 按池子和输入代币查找边
+    pub fn get_edge(&self, pool_id: &str, from_addr: &str) -> Option<&Edge> {
         self.edges
-            .entry(from_addr)
-            .or_insert_with(Vec::new)
-            .push(edge);
+            .get(from_addr)?
+            .iter()
+            .find(|edge| edge.pool_id == pool_id)
+    }
+
+    /// 批量应用刷新得到的市场数据，所有变化只递增一次图版本号
+    ///
+    /// 刷新成功的边按报价的汇率和流动性重建曲线并重新计算权重；刷新失败的边保留原有数据并标记为过期。
+    /// 返回发生变化的边数。
+    pub fn apply_market_data(&mut self, updates: &[EdgeMarketData]) -> usize {
+        let mut changed_pools = Vec::new();
+        for update in updates {
+            let Some(edge) = self
+                .edges
+                .get_mut(&update.from_token)
+                .and_then(|edges| edges.iter_mut().find(|edge| edge.pool_id == update.pool_id))
+            else {
+                continue;
+            };
+
+            let changed = match &update.quote {
+                Some(quote) if quote.exchange_rate > dec!(0) && quote.liquidity_available > dec!(0) => {
+                    let curve = edge
+                        .curve
+                        .with_market_data(quote.exchange_rate, quote.liquidity_available);
+                    let changed = edge.stale || curve != edge.curve;
+                    edge.curve = curve;
+                    edge.stale = false;
+                    edge.recompute();
+                    changed
+                }
+                // 报价缺失或无效时保留上一次的数据
+                _ => !std::mem::replace(&mut edge.stale, true),
+            };
+            if changed {
+                changed_pools.push(update.pool_id.clone());
+            }
+        }
+
+        if !changed_pools.is_empty() {
+            self.bump_version(changed_pools.iter().map(String::as_str));
+        }
+        changed_pools.len()
+    }
+
+    /// 最近一次市场数据刷新失败的边数
+    pub fn stale_edge_count(&self) -> usize {
+        self.edges.values().flatten().filter(|edge| edge.stale).count()
     }
 
     /// 用示例数据初始化图（用于演示）
@@ -82,115 +322,172 @@ impl RoutingGraph {
         };
 
         // USDC -> SOL 边
-        self.add_edge(Edge {
-            from_token: usdc.clone(),
-            to_token: sol.clone(),
-            dex_platform: raydium.clone(),
-            exchange_rate: dec!(0.001),   // 1 SOL = 1000 USDC
-            liquidity: dec!(1000000),     // 100万 USDC 流动性
-            max_trade_size: dec!(500000), // 50万 USDC 最大交易
-            min_trade_size: dec!(10),     // 10 USDC 最小交易
-            weight: -f64::ln(0.001),      // -log(exchange_rate)
-        });
-
-        self.add_edge(Edge {
-            from_token: usdc.clone(),
-            to_token: sol.clone(),
-            dex_platform: orca.clone(),
-            exchange_rate: dec!(0.00101), // 稍差的汇率
-            liquidity: dec!(500000),      // 50万 USDC 流动性
-            max_trade_size: dec!(200000), // 20万 USDC 最大交易
-            min_trade_size: dec!(10),     // 10 USDC 最小交易
-            weight: -f64::ln(0.00101),
-        });
-
-        self.add_edge(Edge {
-            from_token: usdc.clone(),
-            to_token: sol.clone(),
-            dex_platform: meteora.clone(),
-            exchange_rate: dec!(0.00102),  // 最差汇率但流动性好
-            liquidity: dec!(2000000),      // 200万 USDC 流动性
-            max_trade_size: dec!(1000000), // 100万 USDC 最大交易
-            min_trade_size: dec!(10),      // 10 USDC 最小交易
-            weight: -f64::ln(0.00102),
-        });
+        self.add_edge(Edge::new(
+            "raydium-usdc-sol".to_string(),
+            usdc.clone(),
+            sol.clone(),
+            raydium.clone(),
+            // 恒定乘积池：1 SOL = 1000 USDC，100万 USDC 流动性
+            Curve::ConstantProduct(ConstantProductCurve::from_rate(dec!(1000000), dec!(0.001))),
+            dec!(500000), // 50万 USDC 最大交易
+            dec!(10),     // 10 USDC 最小交易
+        ));
+
+        self.add_edge(Edge::new(
+            "orca-usdc-sol".to_string(),
+            usdc.clone(),
+            sol.clone(),
+            orca.clone(),
+            // 集中流动性池：稍差的汇率，50万 USDC 虚拟流动性，价格区间 [0.0008, 0.0012]
+            Curve::ConcentratedLiquidity(ConcentratedLiquidityCurve::from_rate(
+                dec!(500000),
+                dec!(0.00101),
+                dec!(0.0008),
+                dec!(0.0012),
+            )),
+            dec!(200000), // 20万 USDC 最大交易
+            dec!(10),     // 10 USDC 最小交易
+        ));
+
+        self.add_edge(Edge::new(
+            "meteora-usdc-sol".to_string(),
+            usdc.clone(),
+            sol.clone(),
+            meteora.clone(),
+            // 恒定乘积池：最差汇率但流动性好，200万 USDC 流动性
+            Curve::ConstantProduct(ConstantProductCurve::from_rate(dec!(2000000), dec!(0.00102))),
+            dec!(1000000), // 100万 USDC 最大交易
+            dec!(10),      // 10 USDC 最小交易
+        ));
 
         // USDC -> RAY 边
-        self.add_edge(Edge {
-            from_token: usdc.clone(),
-            to_token: ray.clone(),
-            dex_platform: raydium.clone(),
-            exchange_rate: dec!(0.5),    // 1 RAY = 0.5 USDC
-            liquidity: dec!(100000),     // 10万 USDC 流动性
-            max_trade_size: dec!(50000), // 5万 USDC 最大交易
-            min_trade_size: dec!(10),    // 10 USDC 最小交易
-            weight: -f64::ln(0.5),
-        });
+        self.add_edge(Edge::new(
+            "raydium-usdc-ray".to_string(),
+            usdc.clone(),
+            ray.clone(),
+            raydium.clone(),
+            // 1 RAY = 0.5 USDC，10万 USDC 流动性
+            Curve::ConstantProduct(ConstantProductCurve::from_rate(dec!(100000), dec!(0.5))),
+            dec!(50000), // 5万 USDC 最大交易
+            dec!(10),    // 10 USDC 最小交易
+        ));
 
         // RAY -> SOL 边
-        self.add_edge(Edge {
-            from_token: ray.clone(),
-            to_token: sol.clone(),
-            dex_platform: orca.clone(),
-            exchange_rate: dec!(0.002),  // 1 SOL = 500 RAY
-            liquidity: dec!(50000),      // 5万 RAY 流动性
-            max_trade_size: dec!(25000), // 2.5万 RAY 最大交易
-            min_trade_size: dec!(1),     // 1 RAY 最小交易
-            weight: -f64::ln(0.002),
-        });
+        self.add_edge(Edge::new(
+            "orca-ray-sol".to_string(),
+            ray.clone(),
+            sol.clone(),
+            orca.clone(),
+            // 1 SOL = 500 RAY，5万 RAY 流动性
+            Curve::ConstantProduct(ConstantProductCurve::from_rate(dec!(50000), dec!(0.002))),
+            dec!(25000), // 2.5万 RAY 最大交易
+            dec!(1),     // 1 RAY 最小交易
+        ));
     }
 
     /// 具有 Metis 改进的增强 Bellman-Ford 算法
-    pub async fn find_optimal_route(&self, request: &RouteRequest) -> Result<Option<Route>> {
+    pub async fn find_optimal_route(&self, request: &RouteRequest) -> Result<RouteSearchResult> {
+        let start_addr = self.get_token_address(&request.input_token)?;
+        let filters = self.resolve_filters(request);
+        let (path, iterations) = self
+            .search_path_with_iterations(
+                request,
+                &start_addr,
+                request.input_amount,
+                &SearchConstraints::new(&filters),
+            )
+            .await?;
+        Ok(RouteSearchResult {
+            route: path.and_then(|path| self.build_route(&path, request.input_amount)),
+            iterations,
+        })
+    }
+
+    /// 在给定约束下搜索从起始代币出发、使输出数量最大的边序列
+    async fn search_path(
+        &self,
+        request: &RouteRequest,
+        start_addr: &str,
+        start_amount: Decimal,
+        constraints: &SearchConstraints<'_>,
+    ) -> Result<Option<Vec<Edge>>> {
+        let (path, _) = self
+            .search_path_with_iterations(request, start_addr, start_amount, constraints)
+            .await?;
+        Ok(path)
+    }
+
+    /// 搜索最优边序列，同时返回实际执行的迭代次数
+    ///
+    /// 按跳数分层进行松弛：第 k 次迭代只从第 k-1 层扩展出第 k 层，
+    /// 因此结果是跳数预算内的最优路由，而不是截断后的无约束最优路由。
+    async fn search_path_with_iterations(
+        &self,
+        request: &RouteRequest,
+        start_addr: &str,
+        start_amount: Decimal,
+        constraints: &SearchConstraints<'_>,
+    ) -> Result<(Option<Vec<Edge>>, usize)> {
         let start_time = std::time::Instant::now();
 
+        let start_symbol = self
+            .nodes
+            .get(start_addr)
+            .map(|token| token.symbol.as_str())
+            .unwrap_or(start_addr);
         info!(
             "🔍 寻找最优路由: {} -> {} ({} {})",
-            request.input_token, request.output_token, request.input_amount, request.input_token
+            start_symbol, request.output_token, start_amount, start_symbol
         );
 
-        // 初始化节点
-        let mut nodes = self.initialize_nodes(&request.input_token)?;
+        let hop_limit = self
+            .hop_limit(request)
+            .saturating_sub(constraints.used_hops);
 
-        // 设置起始节点
-        let start_addr = self.get_token_address(&request.input_token)?;
-        if let Some(start_node) = nodes.get_mut(&start_addr) {
+        // 第 0 层只有起始节点
+        let mut start_layer = self.initialize_nodes(start_addr)?;
+        if let Some(start_node) = start_layer.get_mut(start_addr) {
             start_node.distance = 0.0;
-            start_node.best_amount = request.input_amount;
+            start_node.best_amount = start_amount;
         }
 
         let mut iteration_state = IterationState {
-            nodes,
+            layers: vec![start_layer],
             improved: true,
             iteration: 0,
-            best_route: None,
         };
 
-        // 具有早期终止的增强 Bellman-Ford 迭代
-        while iteration_state.improved && iteration_state.iteration < request.max_iterations {
+        // 分层 Bellman-Ford 迭代，每次迭代增加一跳
+        while iteration_state.improved && iteration_state.iteration < hop_limit {
             iteration_state.improved = false;
             iteration_state.iteration += 1;
+            iteration_state.layers.push(self.initialize_nodes(start_addr)?);
 
             debug!("🔄 Bellman-Ford 迭代 {}", iteration_state.iteration);
 
-            // 处理具有流动性约束的所有边
+            // 只从上一层可达的节点出发处理边
+            let previous_layer = iteration_state.iteration - 1;
             for (from_addr, edges) in &self.edges {
-                if let Some(_from_node) = iteration_state.nodes.get(from_addr) {
+                let reachable = iteration_state.layers[previous_layer]
+                    .get(from_addr)
+                    .is_some_and(|node| node.distance != f64::INFINITY);
+                if reachable {
                     for edge in edges {
-                        self.relax_edge(&mut iteration_state, edge, request).await?;
+                        self.relax_edge(&mut iteration_state, edge, constraints)
+                            .await?;
                     }
                 }
             }
 
-            // 如果没有改进则早期终止
+            // 新的一层没有任何可达节点时提前终止
             if !iteration_state.improved {
                 debug!("✅ 迭代 {} 中没有改进，提前终止", iteration_state.iteration);
                 break;
             }
         }
 
-        // 提取找到的最优路由
-        let route = self.extract_route(&iteration_state, request)?;
+        // 提取找到的最优路径
+        let path = self.extract_path(&iteration_state, request, constraints.used_hops)?;
 
         let execution_time = start_time.elapsed().as_millis() as u64;
         info!(
@@ -198,7 +495,73 @@ impl RoutingGraph {
             execution_time, iteration_state.iteration
         );
 
-        Ok(route)
+        Ok((path, iteration_state.iteration))
+    }
+
+    /// 请求允许的最大跳数，同时受最大迭代次数限制
+    fn hop_limit(&self, request: &RouteRequest) -> usize {
+        if request.filters.only_direct_routes {
+            return 1;
+        }
+        request
+            .max_hops
+            .unwrap_or(self.config.max_hops)
+            .min(request.max_iterations)
+    }
+
+    /// 把请求的过滤条件解析为地址集合，每次搜索只解析一次
+    ///
+    /// DEX 平台按图中出现的平台逐一判定；中间代币条目经代币注册表解析为 mint，
+    /// 无法解析的条目不匹配任何代币。
+    fn resolve_filters(&self, request: &RouteRequest) -> ResolvedFilters {
+        let filters = &request.filters;
+        let resolve = |entries: &[String]| -> HashSet<String> {
+            entries
+                .iter()
+                .filter_map(|entry| self.registry.resolve(entry).ok())
+                .map(|token| token.address.clone())
+                .collect()
+        };
+
+        let restricts_dexes = filters.allowed_dexes.is_some() || !filters.excluded_dexes.is_empty();
+        ResolvedFilters {
+            allowed_dexes: restricts_dexes.then(|| {
+                self.edges
+                    .values()
+                    .flatten()
+                    .map(|edge| &edge.dex_platform)
+                    .filter(|dex| filters.allows_dex(dex))
+                    .map(|dex| dex.address.clone())
+                    .collect()
+            }),
+            allowed_intermediates: filters.allowed_intermediate_tokens.as_deref().map(resolve),
+            excluded_intermediates: resolve(&filters.excluded_intermediate_tokens),
+            endpoints: resolve(&[request.input_token.clone(), request.output_token.clone()]),
+        }
+    }
+
+    /// 边是否满足请求的 DEX 平台和中间代币限制，并按配置跳过过期的边
+    fn edge_allowed(&self, edge: &Edge, filters: &ResolvedFilters) -> bool {
+        if edge.stale && self.config.exclude_stale_edges {
+            return false;
+        }
+        if filters
+            .allowed_dexes
+            .as_ref()
+            .is_some_and(|allowed| !allowed.contains(&edge.dex_platform.address))
+        {
+            return false;
+        }
+        [&edge.from_token.address, &edge.to_token.address]
+            .into_iter()
+            .all(|addr| {
+                filters.endpoints.contains(addr)
+                    || (filters
+                        .allowed_intermediates
+                        .as_ref()
+                        .is_none_or(|allowed| allowed.contains(addr))
+                        && !filters.excluded_intermediates.contains(addr))
+            })
     }
 
     /// 为 Bellman-Ford 初始化图节点
@@ -211,9 +574,8 @@ impl RoutingGraph {
                 GraphNode {
                     token: token.clone(),
                     distance: f64::INFINITY,
-                    predecessor: None,
+                    predecessor_edge: None,
                     best_amount: dec!(0),
-                    liquidity_used: dec!(0),
                 },
             );
         }
@@ -222,51 +584,68 @@ impl RoutingGraph {
     }
 
     /// 具有流动性约束的增强松弛操作
+    ///
+    /// 从上一层的节点状态松弛到当前层，以请求输入数量经过池子曲线后在目标节点的
+    /// 实际输出数量作为比较依据，而不是静态的 -log(exchange_rate) 权重。
     async fn relax_edge(
         &self,
         state: &mut IterationState,
         edge: &Edge,
-        _request: &RouteRequest,
+        constraints: &SearchConstraints<'_>,
     ) -> Result<()> {
         let from_addr = &edge.from_token.address;
         let to_addr = &edge.to_token.address;
 
-        if let Some(from_node) = state.nodes.get(from_addr) {
+        if constraints.excluded_pools.contains(&edge.pool_id)
+            || constraints.excluded_tokens.contains(from_addr)
+            || constraints.excluded_tokens.contains(to_addr)
+            || !self.edge_allowed(edge, constraints.filters)
+        {
+            return Ok(());
+        }
+
+        let layer = state.iteration;
+        let (previous_layers, current_layers) = state.layers.split_at_mut(layer);
+
+        if let Some(from_node) = previous_layers[layer - 1].get(from_addr) {
             if from_node.distance == f64::INFINITY {
                 return Ok(()); // 跳过不可达节点
             }
 
-            // 计算潜在改进
-            let new_distance = from_node.distance + edge.weight;
-            let potential_amount = from_node.best_amount * edge.exchange_rate;
-
-            // 应用流动性约束
-            let available_liquidity = edge.liquidity - edge.max_trade_size.min(edge.liquidity);
-            let constrained_amount = potential_amount.min(available_liquidity);
-
-            // 检查此路径是否更好
-            if let Some(to_node) = state.nodes.get_mut(to_addr) {
-                if new_distance < to_node.distance && constrained_amount > dec!(0) {
-                    // 额外约束：价格影响、最小交易规模
-                    if constrained_amount >= edge.min_trade_size
-                        && self.calculate_price_impact(edge, constrained_amount)
-                            <= self.config.max_price_impact
-                    {
-                        to_node.distance = new_distance;
-                        to_node.predecessor = Some(from_addr.clone());
-                        to_node.best_amount = constrained_amount;
-                        to_node.liquidity_used = constrained_amount;
-
-                        state.improved = true;
-
-                        debug!(
-                            "🔄 松弛边: {} -> {} (数量: {}, 距离: {})",
-                            edge.from_token.symbol,
-                            edge.to_token.symbol,
-                            constrained_amount,
-                            new_distance
-                        );
-                    }
+            // 只扩展简单路径：目标代币已在当前路径上时跳过，避免套利环路破坏前驱链
+            if Self::path_contains(previous_layers, from_addr, to_addr) {
+                return Ok(());
+            }
+
+            let amount_in = from_node.best_amount;
+
+            // 通过池子曲线计算精确输出，不满足交易约束时跳过
+            let amount_out = match self.edge_output(edge, amount_in) {
+                Some(amount_out) => amount_out,
+                None => return Ok(()),
+            };
+
+            // 距离为到达该节点的累计有效汇率的负对数：-log(amount_out / start_amount)
+            let new_distance =
+                from_node.distance + MathUtils::calculate_edge_weight(amount_out / amount_in);
+
+            // 以目标节点上的实际到手数量判断此路径是否更好
+            if let Some(to_node) = current_layers[0].get_mut(to_addr) {
+                if amount_out > to_node.best_amount && amount_out > dec!(0) {
+                    to_node.distance = new_distance;
+                    to_node.predecessor_edge = Some(edge.clone());
+                    to_node.best_amount = amount_out;
+
+                    state.improved = true;
+
+                    debug!(
+                        "🔄 松弛边: {} -> {} (第 {} 跳, 数量: {}, 距离: {})",
+                        edge.from_token.symbol,
+                        edge.to_token.symbol,
+                        layer,
+                        amount_out,
+                        new_distance
+                    );
                 }
             }
         }
@@ -274,124 +653,305 @@ impl RoutingGraph {
         Ok(())
     }
 
+    /// 检查到达最后一层中 `from_addr` 的最优路径上是否包含 `target`
+    fn path_contains(layers: &[HashMap<String, GraphNode>], from_addr: &str, target: &str) -> bool {
+        let mut current = from_addr;
+        for layer in layers.iter().rev() {
+            if current == target {
+                return true;
+            }
+            match layer
+                .get(current)
+                .and_then(|node| node.predecessor_edge.as_ref())
+            {
+                Some(edge) => current = &edge.from_token.address,
+                None => return false,
+            }
+        }
+        current == target
+    }
+
+    /// 计算单条边在给定输入下的输出数量
+    fn edge_output(&self, edge: &Edge, amount_in: Decimal) -> Option<Decimal> {
+        self.edge_output_on(edge, &edge.curve, amount_in)
+    }
+
+    /// 计算单条边在给定池子状态下的输出数量
+    ///
+    /// 先按 DEX 费率从输入中扣除费用，剩余部分经过池子曲线。
+    /// 检查最小/最大交易规模、曲线可成交性和价格影响上限，不满足时返回 `None`。
+    fn edge_output_on(&self, edge: &Edge, curve: &Curve, amount_in: Decimal) -> Option<Decimal> {
+        if amount_in < edge.min_trade_size || amount_in > edge.max_trade_size {
+            return None;
+        }
+        let amount_after_fee = edge.amount_after_fee(amount_in);
+        if self.calculate_price_impact(curve, amount_after_fee) > self.config.max_price_impact {
+            return None;
+        }
+        curve
+            .get_amount_out(amount_after_fee)
+            .filter(|amount_out| *amount_out > dec!(0))
+    }
+
+    /// 计算单条边产出给定数量所需的输入数量
+    ///
+    /// 所需输入包含交易费用，按输入代币的小数位向上取整，并与 `edge_output_on` 使用相同的交易约束。
+    fn edge_input(&self, edge: &Edge, amount_out: Decimal) -> Option<Decimal> {
+        let amount_after_fee = edge.curve.get_amount_in(amount_out)?;
+        let fee_retention = dec!(1) - edge.dex_platform.fee_rate;
+        if fee_retention <= dec!(0) {
+            return None;
+        }
+        let amount_in = (amount_after_fee / fee_retention).round_dp_with_strategy(
+            edge.from_token.decimals as u32,
+            RoundingStrategy::AwayFromZero,
+        );
+        if amount_in <= dec!(0)
+            || amount_in < edge.min_trade_size
+            || amount_in > edge.max_trade_size
+        {
+            return None;
+        }
+        if self.calculate_price_impact(&edge.curve, amount_after_fee) > self.config.max_price_impact
+        {
+            return None;
+        }
+        Some(amount_in)
+    }
+
     /// 计算给定交易规模的价格影响
-    fn calculate_price_impact(&self, edge: &Edge, trade_amount: Decimal) -> Decimal {
-        // 简单的线性价格影响模型
-        // 实际应用中，这将使用实际的 DEX 曲线（恒定乘积等）
-        let impact_ratio = trade_amount / edge.liquidity;
-        impact_ratio * dec!(0.5) // 比率的 50% 作为价格影响
+    fn calculate_price_impact(&self, curve: &Curve, trade_amount: Decimal) -> Decimal {
+        // 由池子曲线给出相对边际价格的损失，无法成交视为 100% 影响
+        curve.price_impact(trade_amount).unwrap_or(dec!(1))
     }
 
-    /// 从 Bellman-Ford 结果中提取最优路由
-    fn extract_route(
+    /// 从 Bellman-Ford 结果中提取最优路径上的边
+    ///
+    /// 在所有层中选出扣除 gas 后净输出最多的一层，再逐层沿前驱边回溯。
+    /// 同一层内的路径跳数相同、gas 成本相同，因此层内仍按输出数量比较。
+    fn extract_path(
         &self,
         state: &IterationState,
         request: &RouteRequest,
-    ) -> Result<Option<Route>> {
+        used_hops: usize,
+    ) -> Result<Option<Vec<Edge>>> {
         let output_addr = self.get_token_address(&request.output_token)?;
+        let gas_rate = self.gas_rate(&output_addr);
+
+        let best_layer = state
+            .layers
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter_map(|(hops, layer)| {
+                layer
+                    .get(&output_addr)
+                    .filter(|node| node.distance != f64::INFINITY)
+                    .map(|node| {
+                        let gas = self.estimate_gas_cost(used_hops + hops);
+                        (hops, node.best_amount - gas * gas_rate.unwrap_or(dec!(0)))
+                    })
+            })
+            .max_by(|(_, a), (_, b)| a.cmp(b))
+            .map(|(hops, _)| hops);
+
+        let Some(hops) = best_layer else {
+            warn!("❌ 未找到到输出代币 {} 的路径", request.output_token);
+            return Ok(None);
+        };
 
-        if let Some(output_node) = state.nodes.get(&output_addr) {
-            if output_node.distance == f64::INFINITY {
-                warn!("❌ 未找到到输出代币 {} 的路径", request.output_token);
+        // 逐层重建路径上的边
+        let mut path = Vec::with_capacity(hops);
+        let mut current_addr = output_addr;
+        for layer in state.layers[1..=hops].iter().rev() {
+            let Some(edge) = layer
+                .get(&current_addr)
+                .and_then(|node| node.predecessor_edge.as_ref())
+            else {
+                warn!("❌ 前驱链不完整，放弃路径重建");
                 return Ok(None);
-            }
+            };
+            path.push(edge.clone());
+            current_addr = edge.from_token.address.clone();
+        }
 
-            // 重建路径
-            let mut segments = Vec::new();
-            let mut current_addr = output_addr.clone();
-            let mut current_amount = output_node.best_amount;
-
-            while let Some(predecessor_addr) = &state.nodes[&current_addr].predecessor {
-                let edge = self.find_edge(predecessor_addr, &current_addr)?;
-                let predecessor_node = &state.nodes[predecessor_addr];
-
-                let input_amount = predecessor_node.best_amount;
-                let output_amount = current_amount;
-                let exchange_rate = output_amount / input_amount;
-                let price_impact = self.calculate_price_impact(edge, input_amount);
-
-                segments.push(PathSegment {
-                    from_token: edge.from_token.clone(),
-                    to_token: edge.to_token.clone(),
-                    dex_platform: edge.dex_platform.clone(),
-                    input_amount,
-                    output_amount,
-                    exchange_rate,
-                    price_impact,
-                });
+        // 反转边以获得正确顺序
+        path.reverse();
 
-                current_addr = predecessor_addr.clone();
-                current_amount = input_amount;
-            }
+        debug!("🧭 最优路径共 {} 跳", path.len());
+        Ok(Some(path))
+    }
 
-            // 反转段以获得正确顺序
-            segments.reverse();
+    /// 沿给定的边序列用池子曲线模拟兑换并构建路由
+    fn build_route(&self, path: &[Edge], input_amount: Decimal) -> Option<Route> {
+        self.build_route_in(path, input_amount, &mut PoolLedger::default())
+    }
 
-            if segments.is_empty() {
-                return Ok(None);
-            }
+    /// 在给定的池子账本上构建路由，并把本路由的成交记入账本
+    fn build_route_in(
+        &self,
+        path: &[Edge],
+        input_amount: Decimal,
+        ledger: &mut PoolLedger,
+    ) -> Option<Route> {
+        if path.is_empty() {
+            return None;
+        }
 
-            let total_input = request.input_amount;
-            let total_output = segments.last().unwrap().output_amount;
-            let effective_rate = total_output / total_input;
-            let total_price_impact = segments.iter().map(|s| s.price_impact).sum();
-            let gas_estimate = self.estimate_gas_cost(&segments);
-
-            Ok(Some(Route {
-                segments,
-                total_input_amount: total_input,
-                total_output_amount: total_output,
-                effective_rate,
-                price_impact: total_price_impact,
-                gas_estimate,
-                split_ratio: None,
-            }))
-        } else {
-            Ok(None)
-        }
-    }
-
-    /// 查找两个代币之间的边
-    fn find_edge(&self, from_addr: &str, to_addr: &str) -> Result<&Edge> {
-        if let Some(edges) = self.edges.get(from_addr) {
-            for edge in edges {
-                if edge.to_token.address == to_addr {
-                    return Ok(edge);
-                }
-            }
+        let mut segments = Vec::with_capacity(path.len());
+        let mut current_amount = input_amount;
+
+        for edge in path {
+            let curve = ledger.curve_for(edge);
+            let output_amount = self.edge_output_on(edge, &curve, current_amount)?;
+            segments.push(PathSegment {
+                pool_id: edge.pool_id.clone(),
+                from_token: edge.from_token.clone(),
+                to_token: edge.to_token.clone(),
+                dex_platform: edge.dex_platform.clone(),
+                input_amount: current_amount,
+                output_amount,
+                fee_amount: edge.fee_amount(current_amount),
+                exchange_rate: output_amount / current_amount,
+                price_impact: self
+                    .calculate_price_impact(&curve, edge.amount_after_fee(current_amount)),
+            });
+            ledger.record_swap(edge, &curve, current_amount)?;
+            current_amount = output_amount;
         }
-        Err(anyhow::anyhow!("未找到边: {} -> {}", from_addr, to_addr))
+
+        let total_output = current_amount;
+        let effective_rate = total_output / input_amount;
+        let total_price_impact = segments.iter().map(|s| s.price_impact).sum();
+        let gas_estimate = self.estimate_gas_cost(segments.len());
+        let net_output_amount =
+            total_output - self.gas_cost_in(segments.len(), &path[path.len() - 1].to_token.address);
+
+        Some(Route {
+            segments,
+            total_input_amount: input_amount,
+            total_output_amount: total_output,
+            effective_rate,
+            price_impact: total_price_impact,
+            gas_estimate,
+            net_output_amount,
+            split_ratio: None,
+        })
     }
 
-    /// 通过符号获取代币地址
-    fn get_token_address(&self, symbol: &str) -> Result<String> {
-        for (addr, token) in &self.nodes {
-            if token.symbol == symbol {
-                return Ok(addr.clone());
-            }
-        }
-        Err(anyhow::anyhow!("未找到代币: {}", symbol))
+    /// 在给定的池子账本上沿边序列模拟兑换，返回最终输出数量
+    fn simulate_path(
+        &self,
+        path: &[Edge],
+        input_amount: Decimal,
+        ledger: &mut PoolLedger,
+    ) -> Option<Decimal> {
+        path.iter().try_fold(input_amount, |amount, edge| {
+            let curve = ledger.curve_for(edge);
+            let amount_out = self.edge_output_on(edge, &curve, amount)?;
+            ledger.record_swap(edge, &curve, amount)?;
+            Some(amount_out)
+        })
+    }
+
+    /// 通过符号、别名或 mint 地址获取代币地址
+    fn get_token_address(&self, query: &str) -> Result<String> {
+        Ok(self.resolve_token(query)?.address.clone())
     }
 
     /// 通过符号获取代币
-    fn get_token_by_symbol(&self, symbol: &str) -> Result<&Token> {
-        for (_, token) in &self.nodes {
-            if token.symbol == symbol {
-                return Ok(token);
-            }
+    pub fn get_token_by_symbol(&self, symbol: &str) -> Result<&Token> {
+        Ok(self.resolve_token(symbol)?)
+    }
+
+    /// 通过符号、别名或 mint 地址解析代币，符号不唯一时返回列出候选的错误
+    pub fn resolve_token(&self, query: &str) -> std::result::Result<&Token, TokenLookupError> {
+        self.registry.resolve(query)
+    }
+
+    /// 估算在一笔交易中执行给定跳数的 gas 成本（以 SOL 计）
+    ///
+    /// 包括每笔交易的基础费用和按计算单元计算的优先费。
+    fn estimate_gas_cost(&self, hops: usize) -> Decimal {
+        let compute_units =
+            self.config.base_compute_units + self.config.compute_units_per_hop * hops as u64;
+        self.config.gas_price
+            + MathUtils::calculate_priority_fee(
+                compute_units,
+                self.config.priority_fee_micro_lamports,
+            )
+    }
+
+    /// 给定跳数的 gas 成本折算为指定代币的数量
+    ///
+    /// 图中没有原生代币到该代币的路径时无法折算，按 0 处理（构建折算表时已记录警告）。
+    fn gas_cost_in(&self, hops: usize, token_addr: &str) -> Decimal {
+        match self.gas_rate(token_addr) {
+            Some(rate) => self.estimate_gas_cost(hops) * rate,
+            None => dec!(0),
         }
-        Err(anyhow::anyhow!("未找到代币: {}", symbol))
     }
 
-    /// 估算路由的 gas 成本
-    fn estimate_gas_cost(&self, segments: &[PathSegment]) -> Decimal {
-        // 简单的 gas 估算：每个 DEX 交互的基础成本
-        let base_gas_per_dex = dec!(0.000001); // 每次 DEX 交互的 SOL
-        let total_gas = base_gas_per_dex * Decimal::from(segments.len());
-        total_gas * self.config.gas_price
+    /// 1 个原生代币可折算的指定代币数量，无法折算时返回 `None`
+    fn gas_rate(&self, token_addr: &str) -> Option<Decimal> {
+        self.gas_rates
+            .get_or_init(|| self.native_conversion_rates())
+            .get(token_addr)
+            .copied()
+    }
+
+    /// 用图中计入费率的现货汇率，求 1 个原生代币可折算的各代币数量
+    ///
+    /// 在默认跳数预算内取汇率乘积最大的路径，不考虑价格影响。每个图版本只计算一次。
+    fn native_conversion_rates(&self) -> HashMap<String, Decimal> {
+        let native = self.config.native_token_address.as_str();
+        let mut rates: HashMap<&str, Decimal> = HashMap::from([(native, dec!(1))]);
+        for _ in 0..self.config.max_hops {
+            let mut next = rates.clone();
+            for edge in self.edges.values().flatten() {
+                let Some(rate) = rates.get(edge.from_token.address.as_str()) else {
+                    continue;
+                };
+                let candidate = *rate
+                    * MathUtils::calculate_effective_rate(
+                        edge.exchange_rate,
+                        edge.dex_platform.fee_rate,
+                        dec!(0),
+                    );
+                if next
+                    .get(edge.to_token.address.as_str())
+                    .is_none_or(|current| candidate > *current)
+                {
+                    next.insert(edge.to_token.address.as_str(), candidate);
+                }
+            }
+            rates = next;
+        }
+
+        let mut unpriced: Vec<&str> = self
+            .nodes
+            .values()
+            .filter(|token| !rates.contains_key(token.address.as_str()))
+            .map(|token| token.symbol.as_str())
+            .collect();
+        if !unpriced.is_empty() {
+            unpriced.sort();
+            warn!(
+                "⛽ 图中没有从原生代币到 {} 的路径，这些代币上的 gas 成本无法折算，按 0 计",
+                unpriced.join(", ")
+            );
+        }
+
+        rates
+            .into_iter()
+            .map(|(addr, rate)| (addr.to_string(), rate))
+            .collect()
     }
 
     /// 寻找分割路由以获得更好的执行
+    ///
+    /// 先用 Yen 算法找出若干条不同的候选路径，再按边际输出相等的原则在候选间分配输入数量，
+    /// 最多保留 `max_splits` 条承接了输入的路径。
     pub async fn find_split_routes(&self, request: &RouteRequest) -> Result<Option<SplitRoute>> {
         if !request.enable_split_routes {
             return Ok(None);
@@ -402,51 +962,77 @@ impl RoutingGraph {
             request.input_amount, request.input_token
         );
 
-        let mut split_routes = Vec::new();
-        let mut remaining_amount = request.input_amount;
         let max_splits = request.max_splits.unwrap_or(3);
+        if max_splits < 2 {
+            return Ok(None);
+        }
 
-        // 尝试找到具有不同数量的多个路由
-        for split_idx in 0..max_splits {
-            if remaining_amount <= dec!(0) {
-                break;
-            }
-
-            // 计算分割数量（递减部分）
-            let split_ratio = if split_idx == 0 {
-                dec!(0.6)
-            } else if split_idx == 1 {
-                dec!(0.3)
-            } else {
-                dec!(0.1)
-            };
+        // 以单个分配块的规模排出候选路径，候选之间可以共用池子；
+        // 各路径最终承接多少由在共享池子账本上进行的分配步骤决定
+        let probe = RouteRequest {
+            input_amount: request.input_amount / Decimal::from(SPLIT_ALLOCATION_STEPS),
+            ..request.clone()
+        };
+        let mut legs = self
+            .k_best_paths(&probe, max_splits + SPLIT_EXTRA_CANDIDATES)
+            .await?;
 
-            let split_amount = request.input_amount * split_ratio;
+        if legs.len() < 2 {
+            debug!("🔀 只有 {} 条候选路径，不进行分割", legs.len());
+            return Ok(None);
+        }
 
-            if split_amount < dec!(10) {
-                // 最小可行数量
-                break;
-            }
+        let mut allocations = self.allocate_split(&legs, request.input_amount);
 
-            let mut split_request = request.clone();
-            split_request.input_amount = split_amount;
+        // 承接输入的候选超过上限时，只保留承接最多的几条并重新分配
+        if allocations.iter().filter(|amount| **amount > dec!(0)).count() > max_splits {
+            let mut ranked: Vec<usize> = (0..legs.len()).collect();
+            ranked.sort_by(|a, b| allocations[*b].cmp(&allocations[*a]));
+            ranked.truncate(max_splits);
+            ranked.sort_unstable();
+            legs = ranked.into_iter().map(|index| legs[index].clone()).collect();
+            allocations = self.allocate_split(&legs, request.input_amount);
+        }
 
-            if let Some(route) = self.find_optimal_route(&split_request).await? {
+        // 在同一账本上依次构建各路由，后面的路由看到前面路由成交后的池子状态
+        let mut ledger = PoolLedger::default();
+        let mut split_routes = Vec::new();
+        for (path, amount) in legs.iter().zip(allocations) {
+            if amount <= dec!(0) {
+                continue;
+            }
+            if let Some(mut route) = self.build_route_in(path, amount, &mut ledger) {
+                route.split_ratio = Some(amount / request.input_amount);
                 split_routes.push(route);
-                remaining_amount -= split_amount;
             }
         }
 
-        if split_routes.is_empty() {
+        if split_routes.len() < 2 {
+            return Ok(None);
+        }
+
+        // 各路径承接不了全部输入时不返回分割路由，否则其输出无法与同等输入的单一路由比较
+        let total_input: Decimal = split_routes.iter().map(|r| r.total_input_amount).sum();
+        if total_input != request.input_amount {
+            warn!(
+                "🔀 分割路由只能承接 {} / {} {}，放弃分割",
+                total_input, request.input_amount, request.input_token
+            );
             return Ok(None);
         }
 
         // 计算组合指标
-        let total_input = split_routes.iter().map(|r| r.total_input_amount).sum();
         let total_output = split_routes.iter().map(|r| r.total_output_amount).sum();
         let effective_rate = total_output / total_input;
-        let total_price_impact = split_routes.iter().map(|r| r.price_impact).sum();
-        let total_gas = split_routes.iter().map(|r| r.gas_estimate).sum();
+        let total_price_impact = split_routes
+            .iter()
+            .map(|r| r.price_impact * r.split_ratio.unwrap_or(dec!(0)))
+            .sum();
+        // 各分支在同一笔交易中执行，只支付一次基础费用
+        let total_hops = split_routes.iter().map(|r| r.segments.len()).sum();
+        let total_gas = self.estimate_gas_cost(total_hops);
+        let output_addr = self.get_token_address(&request.output_token)?;
+        let net_output_amount = total_output - self.gas_cost_in(total_hops, &output_addr);
 
         Ok(Some(SplitRoute {
             routes: split_routes,
@@ -455,6 +1041,1075 @@ impl RoutingGraph {
             effective_rate,
             price_impact: total_price_impact,
             gas_estimate: total_gas,
+            net_output_amount,
         }))
     }
+
+    /// 固定输出数量的反向搜索：寻找得到期望输出所需输入最少的路由
+    ///
+    /// 从输出代币出发沿边反向松弛，每个节点记录从该节点出发到达输出代币所需的最少数量，
+    /// 所需数量由各边曲线的反函数计算。
+    pub async fn find_exact_out_route(&self, request: &RouteRequest) -> Result<RouteSearchResult> {
+        let output_amount = request
+            .output_amount
+            .ok_or_else(|| anyhow::anyhow!("ExactOut 模式需要指定输出数量"))?;
+        let start_addr = self.get_token_address(&request.input_token)?;
+        let output_addr = self.get_token_address(&request.output_token)?;
+        let filters = self.resolve_filters(request);
+
+        info!(
+            "🔍 寻找固定输出路由: {} -> {} ({} {})",
+            request.input_token, request.output_token, output_amount, request.output_token
+        );
+
+        // 按跳数分层：第 k 层记录代币地址 -> (恰好 k 跳到达输出代币所需的最少数量, 下一条边)
+        let mut layers: Vec<HashMap<String, (Decimal, Option<Edge>)>> =
+            vec![HashMap::from([(output_addr.clone(), (output_amount, None))])];
+
+        let hop_limit = self.hop_limit(request);
+        while layers.len() <= hop_limit {
+            let iteration = layers.len();
+            debug!("🔄 反向 Bellman-Ford 迭代 {}", iteration);
+
+            let previous = &layers[iteration - 1];
+            let mut layer: HashMap<String, (Decimal, Option<Edge>)> = HashMap::new();
+            for edge in self.edges.values().flatten() {
+                let from_addr = &edge.from_token.address;
+                let to_addr = &edge.to_token.address;
+
+                let Some((needed_out, _)) = previous.get(to_addr) else {
+                    continue;
+                };
+
+                if !self.edge_allowed(edge, &filters) {
+                    continue;
+                }
+
+                // 只扩展简单路径
+                if Self::successor_chain_contains(&layers, to_addr, from_addr) {
+                    continue;
+                }
+
+                let Some(amount_in) = self.edge_input(edge, *needed_out) else {
+                    continue;
+                };
+
+                if layer
+                    .get(from_addr)
+                    .is_none_or(|(current, _)| amount_in < *current)
+                {
+                    layer.insert(from_addr.clone(), (amount_in, Some(edge.clone())));
+                }
+            }
+
+            if layer.is_empty() {
+                break;
+            }
+            layers.push(layer);
+        }
+        let iterations = layers.len() - 1;
+
+        // 选出所需输入加上折算为输入代币的 gas 成本最少的一层，再从输入代币沿后继边逐层重建路径
+        let best_layer = layers
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter_map(|(hops, layer)| layer.get(&start_addr).map(|(amount, _)| (hops, *amount)))
+            .min_by_key(|(hops, amount)| *amount + self.gas_cost_in(*hops, &start_addr));
+
+        let Some((hops, input_amount)) = best_layer else {
+            warn!("❌ 未找到到输出代币 {} 的固定输出路径", request.output_token);
+            return Ok(RouteSearchResult {
+                route: None,
+                iterations,
+            });
+        };
+
+        let mut path = Vec::with_capacity(hops);
+        let mut current_addr = start_addr;
+        for layer in layers[1..=hops].iter().rev() {
+            let Some((_, Some(edge))) = layer.get(&current_addr) else {
+                warn!("❌ 后继链不完整，放弃路径重建");
+                return Ok(RouteSearchResult {
+                    route: None,
+                    iterations,
+                });
+            };
+            path.push(edge.clone());
+            current_addr = edge.to_token.address.clone();
+        }
+
+        info!(
+            "⏱️  固定输出路由查找完成 ({} 跳)，需要输入 {} {}",
+            hops, input_amount, request.input_token
+        );
+
+        Ok(RouteSearchResult {
+            route: self.build_route(&path, input_amount),
+            iterations,
+        })
+    }
+
+    /// 检查最后一层中从 `from_addr` 出发到达输出代币的后继链上是否包含 `target`
+    fn successor_chain_contains(
+        layers: &[HashMap<String, (Decimal, Option<Edge>)>],
+        from_addr: &str,
+        target: &str,
+    ) -> bool {
+        let mut current = from_addr;
+        for layer in layers.iter().rev() {
+            if current == target {
+                return true;
+            }
+            match layer.get(current).and_then(|(_, edge)| edge.as_ref()) {
+                Some(edge) => current = &edge.to_token.address,
+                None => return false,
+            }
+        }
+        current == target
+    }
+
+    /// 用 Yen 算法寻找净输出最高的前 K 条不同单一路径路由
+    pub async fn find_k_best_routes(&self, request: &RouteRequest, k: usize) -> Result<Vec<Route>> {
+        info!(
+            "🏅 寻找前 {} 条路由: {} -> {}",
+            k, request.input_token, request.output_token
+        );
+
+        Ok(self
+            .k_best_paths(request, k)
+            .await?
+            .iter()
+            .filter_map(|path| self.build_route(path, request.input_amount))
+            .collect())
+    }
+
+    /// Yen 算法：按扣除 gas 后的净输出从高到低返回至多 `k` 条不同的边序列
+    ///
+    /// 每条新路径由已有路径的某个前缀（根路径）加上一段偏离搜索（支路）组成：
+    /// 支路不得使用与根路径相同前缀的已有路径的下一条边，也不得回到根路径上的代币。
+    async fn k_best_paths(&self, request: &RouteRequest, k: usize) -> Result<Vec<Vec<Edge>>> {
+        if k == 0 {
+            return Ok(Vec::new());
+        }
+
+        let start_addr = self.get_token_address(&request.input_token)?;
+        let filters = self.resolve_filters(request);
+        let Some(best) = self
+            .search_path(
+                request,
+                &start_addr,
+                request.input_amount,
+                &SearchConstraints::new(&filters),
+            )
+            .await?
+        else {
+            return Ok(Vec::new());
+        };
+
+        let mut accepted: Vec<Vec<Edge>> = vec![best];
+        let mut candidates: Vec<(Vec<Edge>, Decimal)> = Vec::new();
+
+        while accepted.len() < k {
+            let previous = accepted.last().cloned().unwrap_or_default();
+
+            for spur_index in 0..previous.len() {
+                let root = &previous[..spur_index];
+                let spur_addr = previous[spur_index].from_token.address.clone();
+
+                // 根路径的输出即支路的起始数量
+                let Some(spur_amount) =
+                    self.simulate_path(root, request.input_amount, &mut PoolLedger::default())
+                else {
+                    continue;
+                };
+
+                let mut constraints = SearchConstraints::new(&filters);
+                for path in &accepted {
+                    if path.len() > spur_index && Self::same_path(&path[..spur_index], root) {
+                        constraints
+                            .excluded_pools
+                            .insert(path[spur_index].pool_id.clone());
+                    }
+                }
+                constraints
+                    .excluded_tokens
+                    .extend(root.iter().map(|edge| edge.from_token.address.clone()));
+                constraints.used_hops = root.len();
+
+                let Some(spur) = self
+                    .search_path(request, &spur_addr, spur_amount, &constraints)
+                    .await?
+                else {
+                    continue;
+                };
+
+                let mut candidate = root.to_vec();
+                candidate.extend(spur);
+                if accepted.iter().any(|path| Self::same_path(path, &candidate))
+                    || candidates.iter().any(|(path, _)| Self::same_path(path, &candidate))
+                {
+                    continue;
+                }
+                // 候选按扣除 gas 后的净输出排序
+                if let Some(output) =
+                    self.simulate_path(&candidate, request.input_amount, &mut PoolLedger::default())
+                {
+                    let output_addr = &candidate[candidate.len() - 1].to_token.address;
+                    let gas = self.gas_cost_in(candidate.len(), output_addr);
+                    candidates.push((candidate, output - gas));
+                }
+            }
+
+            // 从候选中取输出最高的一条
+            let Some(best_index) = candidates
+                .iter()
+                .enumerate()
+                .max_by(|(_, a), (_, b)| a.1.cmp(&b.1))
+                .map(|(index, _)| index)
+            else {
+                break;
+            };
+            accepted.push(candidates.swap_remove(best_index).0);
+        }
+
+        Ok(accepted)
+    }
+
+    /// 两条边序列是否经过相同的池子和方向
+    fn same_path(a: &[Edge], b: &[Edge]) -> bool {
+        a.len() == b.len()
+            && a.iter().zip(b).all(|(x, y)| {
+                x.pool_id == y.pool_id && x.from_token.address == y.from_token.address
+            })
+    }
+
+    /// 按边际输出相等的原则在各路径间分配输入数量
+    ///
+    /// 将输入拆成等额小块，每一块都分给使组合总输出增加最多的路径（注水法）。
+    /// 没有路径能承接下一块时提前停止，此时分配总额小于 `total_amount`。
+    /// 组合输出在共享的池子账本上计算，共用池子的路径会看到彼此造成的价格影响。
+    /// 对于凹的输出函数，这使各路径最终的边际汇率近似相等。
+    fn allocate_split(&self, legs: &[Vec<Edge>], total_amount: Decimal) -> Vec<Decimal> {
+        let mut allocations = vec![dec!(0); legs.len()];
+
+        // 每块不得小于各路径首跳的最小交易规模
+        let min_chunk = legs
+            .iter()
+            .filter_map(|path| path.first().map(|edge| edge.min_trade_size))
+            .max()
+            .unwrap_or(dec!(0));
+        let mut steps = SPLIT_ALLOCATION_STEPS;
+        if min_chunk > dec!(0) {
+            let max_steps = (total_amount / min_chunk).floor();
+            steps = steps.min(max_steps.try_into().unwrap_or(SPLIT_ALLOCATION_STEPS));
+        }
+        if steps == 0 {
+            return allocations;
+        }
+
+        let chunk = total_amount / Decimal::from(steps);
+        let mut current_output = dec!(0);
+        let mut allocated = dec!(0);
+
+        for step in 0..steps {
+            let amount = if step + 1 == steps {
+                total_amount - allocated
+            } else {
+                chunk
+            };
+
+            let mut best: Option<(usize, Decimal)> = None;
+            for index in 0..legs.len() {
+                let mut trial = allocations.clone();
+                trial[index] += amount;
+                let Some(output) = self.evaluate_split(legs, &trial) else {
+                    continue;
+                };
+                if best.is_none_or(|(_, best_output)| output > best_output) {
+                    best = Some((index, output));
+                }
+            }
+
+            match best {
+                Some((index, output)) => {
+                    debug!(
+                        "🔀 第 {} 块分配给路径 {} (边际输出: {})",
+                        step,
+                        index,
+                        output - current_output
+                    );
+                    allocations[index] += amount;
+                    current_output = output;
+                    allocated += amount;
+                }
+                None => {
+                    debug!("🔀 没有路径能够承接更多输入，分配在第 {} 块停止", step);
+                    break;
+                }
+            }
+        }
+
+        allocations
+    }
+
+    /// 在共享的池子账本上计算一组分配的组合总输出
+    fn evaluate_split(&self, legs: &[Vec<Edge>], allocations: &[Decimal]) -> Option<Decimal> {
+        let mut ledger = PoolLedger::default();
+        let mut total_output = dec!(0);
+        for (path, amount) in legs.iter().zip(allocations) {
+            if *amount > dec!(0) {
+                total_output += self.simulate_path(path, *amount, &mut ledger)?;
+            }
+        }
+        Some(total_output)
+    }
+
+    /// 检测图中的套利环路
+    ///
+    /// 在 -log(exchange_rate) 权重上运行从虚拟源点出发的 Bellman-Ford，第 V 轮仍能松弛的边
+    /// 说明存在负权环（汇率乘积大于 1）。每个环路按使利润最大的规模模拟成交，
+    /// 只返回扣除费用和 gas 后仍有利润的环路，按净利润从高到低排序。
+    pub fn find_arbitrage_cycles(&self) -> Result<Vec<ArbitrageCycle>> {
+        info!("🔁 检测套利环路 ({} 个代币)", self.nodes.len());
+
+        let mut excluded_pools = HashSet::new();
+        let mut seen = HashSet::new();
+        let mut cycles = Vec::new();
+
+        // 每轮排除已发现环路的池子，以便暴露被其掩盖的其他环路
+        for round in 0..MAX_ARBITRAGE_ROUNDS {
+            let detected = self.detect_negative_cycles(&excluded_pools);
+            if detected.is_empty() {
+                break;
+            }
+            debug!("🔁 第 {} 轮发现 {} 个负权环", round + 1, detected.len());
+
+            for path in detected {
+                excluded_pools.extend(path.iter().map(|edge| edge.pool_id.clone()));
+
+                let mut key: Vec<String> = path.iter().map(|edge| edge.pool_id.clone()).collect();
+                key.sort();
+                if !seen.insert(key) {
+                    continue;
+                }
+
+                if let Some(cycle) = self.evaluate_cycle(&path) {
+                    if cycle.net_profit > dec!(0) {
+                        cycles.push(cycle);
+                    }
+                }
+            }
+        }
+
+        cycles.sort_by_key(|cycle| std::cmp::Reverse(cycle.net_profit));
+        info!("🔁 发现 {} 个有利可图的套利环路", cycles.len());
+        Ok(cycles)
+    }
+
+    /// 用 Bellman-Ford 检测负权环，返回环路上的边序列
+    fn detect_negative_cycles(&self, excluded_pools: &HashSet<String>) -> Vec<Vec<Edge>> {
+        let edges: Vec<&Edge> = self
+            .edges
+            .values()
+            .flatten()
+            .filter(|edge| !excluded_pools.contains(&edge.pool_id))
+            .filter(|edge| {
+                self.nodes.contains_key(&edge.from_token.address)
+                    && self.nodes.contains_key(&edge.to_token.address)
+            })
+            .collect();
+
+        // 虚拟源点到所有节点的距离均为 0
+        let mut distance: HashMap<&str, f64> =
+            self.nodes.keys().map(|addr| (addr.as_str(), 0.0)).collect();
+        let mut predecessor: HashMap<&str, &Edge> = HashMap::new();
+        let mut updated = Vec::new();
+
+        for _ in 0..self.nodes.len() {
+            updated.clear();
+            for edge in &edges {
+                let from = edge.from_token.address.as_str();
+                let to = edge.to_token.address.as_str();
+                let candidate = distance[from] + edge.weight;
+                if candidate < distance[to] - ARBITRAGE_EPSILON {
+                    distance.insert(to, candidate);
+                    predecessor.insert(to, edge);
+                    updated.push(to);
+                }
+            }
+            if updated.is_empty() {
+                return Vec::new();
+            }
+        }
+
+        // 第 V 轮仍被更新的节点可回溯到负权环
+        let mut cycles = Vec::new();
+        let mut in_cycle = HashSet::new();
+        for &start in &updated {
+            // 回溯 V 步，确保落在环上
+            let mut node = start;
+            for _ in 0..self.nodes.len() {
+                match predecessor.get(node) {
+                    Some(edge) => node = edge.from_token.address.as_str(),
+                    None => break,
+                }
+            }
+            if in_cycle.contains(node) {
+                continue;
+            }
+
+            let anchor = node;
+            let mut path = Vec::new();
+            while let Some(edge) = predecessor.get(node) {
+                in_cycle.insert(node);
+                path.push((*edge).clone());
+                node = edge.from_token.address.as_str();
+                if node == anchor || path.len() > self.nodes.len() {
+                    break;
+                }
+            }
+
+            if node == anchor && !path.is_empty() {
+                path.reverse();
+                cycles.push(path);
+            }
+        }
+
+        cycles
+    }
+
+    /// 在使利润最大的规模上模拟环路成交
+    ///
+    /// 利润函数对凹曲线是单峰的，在首跳允许的交易规模内做三分搜索；
+    /// gas 成本与规模无关，在选定规模后折算为起始代币从净利润中扣除。
+    fn evaluate_cycle(&self, path: &[Edge]) -> Option<ArbitrageCycle> {
+        let first = path.first()?;
+        let profit = |amount: Decimal| {
+            self.simulate_path(path, amount, &mut PoolLedger::default())
+                .map(|output| output - amount)
+        };
+
+        let mut low = first.min_trade_size.max(ARBITRAGE_MIN_AMOUNT);
+        let mut high = first.max_trade_size.min(first.liquidity);
+        if high <= low {
+            return None;
+        }
+        for _ in 0..ARBITRAGE_SEARCH_ITERATIONS {
+            let third = (high - low) / dec!(3);
+            let left = low + third;
+            let right = high - third;
+            // 无法成交的规模视为无穷亏损，单峰性仍然成立
+            match (profit(left), profit(right)) {
+                (Some(l), Some(r)) if l >= r => high = right,
+                (Some(_), None) => high = right,
+                (None, _) => high = left,
+                _ => low = left,
+            }
+        }
+
+        let amount = (low + high) / dec!(2);
+        let route = self.build_route(path, amount)?;
+        let trade_profit = route.total_output_amount - route.total_input_amount;
+        let gas_cost = self.gas_cost_in(route.segments.len(), &first.from_token.address);
+        // 各段费用按该段输入与起始数量的比例折算为起始代币
+        let total_fees = route
+            .segments
+            .iter()
+            .map(|segment| {
+                segment.fee_amount * route.total_input_amount / segment.input_amount
+            })
+            .sum::<Decimal>();
+        let gas_estimate = route.gas_estimate;
+
+        Some(ArbitrageCycle {
+            net_profit: trade_profit - gas_cost,
+            gross_profit: trade_profit + total_fees,
+            total_fees,
+            gas_estimate,
+            route,
+        })
+    }
+}
+
+/// 分割路由分配时将输入拆成的块数
+const SPLIT_ALLOCATION_STEPS: u32 = 100;
+
+/// 分割路由在 `max_splits` 之外额外考察的候选路径数
+const SPLIT_EXTRA_CANDIDATES: usize = 2;
+
+/// 套利检测的最大轮数
+const MAX_ARBITRAGE_ROUNDS: usize = 8;
+
+/// 判定负权环时忽略的浮点误差（同一池子往返的权重和应为 0）
+const ARBITRAGE_EPSILON: f64 = 1e-9;
+
+/// 套利规模三分搜索的迭代次数
+const ARBITRAGE_SEARCH_ITERATIONS: usize = 60;
+
+/// 套利规模搜索的最小输入数量
+const ARBITRAGE_MIN_AMOUNT: Decimal = dec!(0.000001);
+
+/// 单次路径搜索的附加约束
+#[derive(Debug, Clone)]
+struct SearchConstraints<'a> {
+    /// 请求的过滤条件
+    filters: &'a ResolvedFilters,
+    /// 不允许使用的池子
+    excluded_pools: HashSet<String>,
+    /// 不允许经过的代币地址
+    excluded_tokens: HashSet<String>,
+    /// 搜索起点之前已经使用的跳数，从跳数预算中扣除
+    used_hops: usize,
+}
+
+impl<'a> SearchConstraints<'a> {
+    fn new(filters: &'a ResolvedFilters) -> Self {
+        Self {
+            filters,
+            excluded_pools: HashSet::new(),
+            excluded_tokens: HashSet::new(),
+            used_hops: 0,
+        }
+    }
+}
+
+/// 解析为地址集合的请求过滤条件
+#[derive(Debug, Default)]
+struct ResolvedFilters {
+    /// 允许使用的 DEX 平台地址，`None` 表示不限制
+    allowed_dexes: Option<HashSet<String>>,
+    /// 允许作为中间代币的 mint 地址，`None` 表示不限制
+    allowed_intermediates: Option<HashSet<String>>,
+    /// 禁止作为中间代币的 mint 地址
+    excluded_intermediates: HashSet<String>,
+    /// 请求的输入和输出代币地址，不受中间代币限制
+    endpoints: HashSet<String>,
+}
+
+/// 单次请求内的池子状态账本
+///
+/// 记录本次请求中已模拟成交的池子状态，使同一池子上的后续成交基于剩余储备计算。
+#[derive(Debug, Default, Clone)]
+struct PoolLedger {
+    /// 池子标识 -> (记录时的输入代币地址, 成交后的曲线)
+    pools: HashMap<String, (String, Curve)>,
+}
+
+impl PoolLedger {
+    /// 获取边所在池子的当前状态（按边的方向）
+    fn curve_for(&self, edge: &Edge) -> Curve {
+        match self.pools.get(&edge.pool_id) {
+            Some((from_addr, curve)) if *from_addr == edge.from_token.address => curve.clone(),
+            Some((_, curve)) => curve.reversed(),
+            None => edge.curve.clone(),
+        }
+    }
+
+    /// 记录一次在边上的成交，交易费用不计入池子曲线的储备
+    fn record_swap(&mut self, edge: &Edge, curve: &Curve, amount_in: Decimal) -> Option<()> {
+        let next = curve.after_swap(edge.amount_after_fee(amount_in))?;
+        self.pools
+            .insert(edge.pool_id.clone(), (edge.from_token.address.clone(), next));
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOL: &str = "So11111111111111111111111111111111111111112";
+
+    fn token(symbol: &str) -> Token {
+        let address = match symbol {
+            "SOL" => SOL.to_string(),
+            _ => format!("{}-mint", symbol.to_lowercase()),
+        };
+        Token {
+            symbol: symbol.to_string(),
+            address,
+            decimals: 6,
+        }
+    }
+
+    fn dex(name: &str) -> DexPlatform {
+        DexPlatform {
+            name: name.to_string(),
+            address: format!("{}-program", name.to_lowercase()),
+            fee_rate: dec!(0.003),
+        }
+    }
+
+    /// 添加恒定乘积池的两个方向，`liquidity` 为 `from` 一侧的储备
+    fn add_pool(
+        graph: &mut RoutingGraph,
+        pool_id: &str,
+        from: &str,
+        to: &str,
+        dex_name: &str,
+        liquidity: Decimal,
+        rate: Decimal,
+    ) {
+        let curve = Curve::ConstantProduct(ConstantProductCurve::from_rate(liquidity, rate));
+        graph.upsert_edge(Edge::new(
+            pool_id.to_string(),
+            token(from),
+            token(to),
+            dex(dex_name),
+            curve.clone(),
+            liquidity,
+            dec!(0),
+        ));
+        graph.upsert_edge(Edge::new(
+            pool_id.to_string(),
+            token(to),
+            token(from),
+            dex(dex_name),
+            curve.reversed(),
+            liquidity * rate,
+            dec!(0),
+        ));
+    }
+
+    fn request(input: &str, output: &str, amount: Decimal) -> RouteRequest {
+        RouteRequest {
+            input_token: input.to_string(),
+            output_token: output.to_string(),
+            input_amount: amount,
+            swap_mode: SwapMode::ExactIn,
+            output_amount: None,
+            slippage_tolerance: dec!(0.005),
+            max_iterations: 5,
+            max_hops: None,
+            enable_split_routes: false,
+            max_splits: None,
+            max_alternatives: None,
+            filters: RouteFilters::default(),
+        }
+    }
+
+    fn pools(route: &Route) -> Vec<&str> {
+        route
+            .segments
+            .iter()
+            .map(|segment| segment.pool_id.as_str())
+            .collect()
+    }
+
+    /// USDC -> SOL 有三条路径：经 Orca 的 RAY（最优）、经 Raydium 的 USDT（次优）和 Raydium 直连（最差）
+    fn filter_graph() -> RoutingGraph {
+        let mut graph = RoutingGraph::new(RouterConfig::default());
+        add_pool(
+            &mut graph,
+            "raydium-usdc-sol",
+            "USDC",
+            "SOL",
+            "Raydium",
+            dec!(1000000),
+            dec!(0.001),
+        );
+        add_pool(
+            &mut graph,
+            "orca-usdc-ray",
+            "USDC",
+            "RAY",
+            "Orca",
+            dec!(1000000),
+            dec!(2),
+        );
+        add_pool(
+            &mut graph,
+            "orca-ray-sol",
+            "RAY",
+            "SOL",
+            "Orca",
+            dec!(2000000),
+            dec!(0.00055),
+        );
+        add_pool(
+            &mut graph,
+            "raydium-usdc-usdt",
+            "USDC",
+            "USDT",
+            "Raydium",
+            dec!(1000000),
+            dec!(1),
+        );
+        add_pool(
+            &mut graph,
+            "raydium-usdt-sol",
+            "USDT",
+            "SOL",
+            "Raydium",
+            dec!(1000000),
+            dec!(0.00105),
+        );
+        graph
+    }
+
+    async fn filtered_route(filters: RouteFilters) -> Vec<String> {
+        let graph = filter_graph();
+        let request = RouteRequest {
+            filters,
+            ..request("USDC", "SOL", dec!(1000))
+        };
+        let route = graph
+            .find_optimal_route(&request)
+            .await
+            .unwrap()
+            .route
+            .expect("应找到路由");
+        pools(&route).into_iter().map(str::to_string).collect()
+    }
+
+    #[tokio::test]
+    async fn unfiltered_route_takes_best_path() {
+        let route = filtered_route(RouteFilters::default()).await;
+        assert_eq!(route, ["orca-usdc-ray", "orca-ray-sol"]);
+    }
+
+    #[tokio::test]
+    async fn excluded_dexes_are_skipped() {
+        let route = filtered_route(RouteFilters {
+            excluded_dexes: vec!["orca".to_string()],
+            ..RouteFilters::default()
+        })
+        .await;
+        assert_eq!(route, ["raydium-usdc-usdt", "raydium-usdt-sol"]);
+    }
+
+    #[tokio::test]
+    async fn allowed_dexes_match_by_address() {
+        let route = filtered_route(RouteFilters {
+            allowed_dexes: Some(vec!["raydium-program".to_string()]),
+            ..RouteFilters::default()
+        })
+        .await;
+        assert_eq!(route, ["raydium-usdc-usdt", "raydium-usdt-sol"]);
+    }
+
+    #[tokio::test]
+    async fn excluded_intermediate_tokens_are_skipped() {
+        let route = filtered_route(RouteFilters {
+            excluded_intermediate_tokens: vec!["RAY".to_string()],
+            ..RouteFilters::default()
+        })
+        .await;
+        assert_eq!(route, ["raydium-usdc-usdt", "raydium-usdt-sol"]);
+    }
+
+    #[tokio::test]
+    async fn allowed_intermediate_tokens_limit_hops() {
+        let route = filtered_route(RouteFilters {
+            allowed_intermediate_tokens: Some(vec!["usdt-mint".to_string()]),
+            ..RouteFilters::default()
+        })
+        .await;
+        assert_eq!(route, ["raydium-usdc-usdt", "raydium-usdt-sol"]);
+    }
+
+    #[tokio::test]
+    async fn only_direct_routes_uses_single_hop() {
+        let route = filtered_route(RouteFilters {
+            only_direct_routes: true,
+            ..RouteFilters::default()
+        })
+        .await;
+        assert_eq!(route, ["raydium-usdc-sol"]);
+    }
+
+    /// USDC -> SOL 有两个汇率相同、各有 100 万 USDC 流动性的池子
+    fn parallel_pools() -> RoutingGraph {
+        let mut graph = RoutingGraph::new(RouterConfig::default());
+        add_pool(
+            &mut graph,
+            "raydium-usdc-sol",
+            "USDC",
+            "SOL",
+            "Raydium",
+            dec!(1000000),
+            dec!(0.001),
+        );
+        add_pool(
+            &mut graph,
+            "orca-usdc-sol",
+            "USDC",
+            "SOL",
+            "Orca",
+            dec!(1000000),
+            dec!(0.001),
+        );
+        graph
+    }
+
+    fn split_request(amount: Decimal) -> RouteRequest {
+        RouteRequest {
+            enable_split_routes: true,
+            max_splits: Some(2),
+            ..request("USDC", "SOL", amount)
+        }
+    }
+
+    #[tokio::test]
+    async fn split_beats_single_route_for_large_orders() {
+        let graph = parallel_pools();
+        let request = split_request(dec!(40000));
+
+        let single = graph
+            .find_optimal_route(&request)
+            .await
+            .unwrap()
+            .route
+            .unwrap();
+        let split = graph
+            .find_split_routes(&request)
+            .await
+            .unwrap()
+            .expect("应找到分割路由");
+
+        assert_eq!(split.routes.len(), 2);
+        assert_eq!(split.total_input_amount, single.total_input_amount);
+        assert!(
+            split.net_output_amount > single.net_output_amount,
+            "分割 {} 应优于单一路由 {}",
+            split.net_output_amount,
+            single.net_output_amount
+        );
+    }
+
+    #[tokio::test]
+    async fn split_ratios_sum_to_one_and_match_leg_inputs() {
+        let graph = parallel_pools();
+        let request = split_request(dec!(40000));
+        let split = graph.find_split_routes(&request).await.unwrap().unwrap();
+
+        let ratios: Vec<Decimal> = split
+            .routes
+            .iter()
+            .map(|route| route.split_ratio.unwrap())
+            .collect();
+        assert_eq!(ratios.iter().sum::<Decimal>(), dec!(1));
+        for (route, ratio) in split.routes.iter().zip(ratios) {
+            assert_eq!(route.total_input_amount, request.input_amount * ratio);
+        }
+        let total_input: Decimal = split
+            .routes
+            .iter()
+            .map(|route| route.total_input_amount)
+            .sum();
+        assert_eq!(total_input, request.input_amount);
+    }
+
+    #[tokio::test]
+    async fn split_rejects_legs_that_cannot_fill_the_input() {
+        let mut graph = parallel_pools();
+        // 每个池子单笔最多承接 1 万 USDC，两条路径合计承接不了 3 万
+        for pool_id in ["raydium-usdc-sol", "orca-usdc-sol"] {
+            let edge = graph.get_edge(pool_id, "usdc-mint").unwrap().clone();
+            graph.upsert_edge(Edge {
+                max_trade_size: dec!(10000),
+                ..edge
+            });
+        }
+
+        assert!(graph
+            .find_split_routes(&split_request(dec!(15000)))
+            .await
+            .unwrap()
+            .is_some());
+        assert!(graph
+            .find_split_routes(&split_request(dec!(30000)))
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn split_legs_through_a_shared_pool_see_moved_reserves() {
+        let mut graph = RoutingGraph::new(RouterConfig::default());
+        add_pool(
+            &mut graph,
+            "usdc-ray",
+            "USDC",
+            "RAY",
+            "Raydium",
+            dec!(1000000),
+            dec!(2),
+        );
+        add_pool(
+            &mut graph,
+            "ray-sol-a",
+            "RAY",
+            "SOL",
+            "Orca",
+            dec!(2000000),
+            dec!(0.0005),
+        );
+        add_pool(
+            &mut graph,
+            "ray-sol-b",
+            "RAY",
+            "SOL",
+            "Meteora",
+            dec!(2000000),
+            dec!(0.0005),
+        );
+        let shared = graph.get_edge("usdc-ray", "usdc-mint").unwrap().clone();
+        let leg_a = vec![
+            shared.clone(),
+            graph.get_edge("ray-sol-a", "ray-mint").unwrap().clone(),
+        ];
+        let leg_b = vec![
+            shared.clone(),
+            graph.get_edge("ray-sol-b", "ray-mint").unwrap().clone(),
+        ];
+
+        let mut ledger = PoolLedger::default();
+        let route_a = graph
+            .build_route_in(&leg_a, dec!(30000), &mut ledger)
+            .unwrap();
+        let route_b = graph
+            .build_route_in(&leg_b, dec!(20000), &mut ledger)
+            .unwrap();
+        let alone_b = graph.build_route(&leg_b, dec!(20000)).unwrap();
+
+        // 第二条路径在共享池子上的成交价更差
+        assert!(route_b.segments[0].output_amount < alone_b.segments[0].output_amount);
+        // 两次成交合计等于一次性成交全部输入（恒定乘积与路径无关）
+        let combined = shared
+            .curve
+            .get_amount_out(
+                shared.amount_after_fee(dec!(30000)) + shared.amount_after_fee(dec!(20000)),
+            )
+            .unwrap();
+        let legs_total = route_a.segments[0].output_amount + route_b.segments[0].output_amount;
+        assert!(
+            (legs_total - combined).abs() < dec!(0.000001),
+            "{} != {}",
+            legs_total,
+            combined
+        );
+    }
+
+    /// 两个 USDC/SOL 池子定价相差约 10%：在 p1 买入 SOL、在 p2 卖出有利可图
+    fn mispriced_pools(config: RouterConfig) -> RoutingGraph {
+        let mut graph = RoutingGraph::new(config);
+        add_pool(
+            &mut graph,
+            "p1",
+            "USDC",
+            "SOL",
+            "Raydium",
+            dec!(1000000),
+            dec!(0.001),
+        );
+        add_pool(
+            &mut graph,
+            "p2",
+            "SOL",
+            "USDC",
+            "Orca",
+            dec!(1000),
+            dec!(1100),
+        );
+        graph
+    }
+
+    #[test]
+    fn arbitrage_finds_planted_mispricing() {
+        let graph = mispriced_pools(RouterConfig::default());
+        let cycles = graph.find_arbitrage_cycles().unwrap();
+
+        assert_eq!(cycles.len(), 1);
+        let cycle = &cycles[0];
+        let mut cycle_pools = pools(&cycle.route);
+        cycle_pools.sort();
+        assert_eq!(cycle_pools, ["p1", "p2"]);
+        assert!(cycle.net_profit > dec!(0));
+        assert!(cycle.gross_profit > cycle.net_profit + cycle.total_fees);
+    }
+
+    #[test]
+    fn arbitrage_skips_cycles_eaten_by_gas() {
+        // 每笔交易 1000 SOL 的 gas 远超两池价差能带来的利润
+        let graph = mispriced_pools(RouterConfig {
+            gas_price: dec!(1000),
+            ..RouterConfig::default()
+        });
+        assert!(graph.find_arbitrage_cycles().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn k_best_routes_are_distinct_and_ordered() {
+        let graph = filter_graph();
+        let routes = graph
+            .find_k_best_routes(&request("USDC", "SOL", dec!(1000)), 3)
+            .await
+            .unwrap();
+
+        let paths: Vec<Vec<&str>> = routes.iter().map(pools).collect();
+        assert_eq!(
+            paths,
+            [
+                vec!["orca-usdc-ray", "orca-ray-sol"],
+                vec!["raydium-usdc-usdt", "raydium-usdt-sol"],
+                vec!["raydium-usdc-sol"],
+            ]
+        );
+        assert!(routes
+            .windows(2)
+            .all(|pair| pair[0].net_output_amount > pair[1].net_output_amount));
+    }
+
+    #[tokio::test]
+    async fn k_best_routes_stop_when_paths_run_out() {
+        let graph = filter_graph();
+        let routes = graph
+            .find_k_best_routes(&request("USDC", "SOL", dec!(1000)), 10)
+            .await
+            .unwrap();
+        assert_eq!(routes.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn exact_out_input_round_trips_through_exact_in() {
+        let graph = filter_graph();
+        let exact_out = RouteRequest {
+            swap_mode: SwapMode::ExactOut,
+            output_amount: Some(dec!(1)),
+            ..request("USDC", "SOL", Decimal::ZERO)
+        };
+        let route = graph
+            .find_exact_out_route(&exact_out)
+            .await
+            .unwrap()
+            .route
+            .expect("应找到固定输出路由");
+        assert!(route.total_output_amount >= dec!(1));
+
+        let exact_in = request("USDC", "SOL", route.total_input_amount);
+        let forward = graph
+            .find_optimal_route(&exact_in)
+            .await
+            .unwrap()
+            .route
+            .unwrap();
+        assert!(
+            forward.total_output_amount >= dec!(1),
+            "输入 {} 只得到 {}",
+            route.total_input_amount,
+            forward.total_output_amount
+        );
+    }
+
+    #[test]
+    fn exact_out_request_parses_without_input_amount() {
+        let request: RouteRequest = serde_json::from_str(
+            r#"{"input_token":"USDC","output_token":"SOL","swap_mode":"exact_out","output_amount":"1","slippage_tolerance":"0.005","max_iterations":5,"enable_split_routes":false}"#,
+        )
+        .unwrap();
+        assert_eq!(request.swap_mode, SwapMode::ExactOut);
+        assert_eq!(request.input_amount, Decimal::ZERO);
+    }
 }
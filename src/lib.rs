@@ -0,0 +1,19 @@
+//! Metis DEX 聚合路由器
+//!
+//! 受 Jupiter v3 启发的 Metis 路由算法实现，用于在 Solana 上的多个 DEX 平台之间优化代币交换。
+
+pub mod breaker;
+pub mod cache;
+pub mod curve;
+pub mod exporter;
+pub mod graph;
+pub mod loader;
+pub mod metrics;
+pub mod provider;
+pub mod quote;
+pub mod registry;
+pub mod routing;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod types;
+pub mod utils;
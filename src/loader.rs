@@ -0,0 +1,570 @@
+use crate::curve::{
+    ConcentratedLiquidityCurve, ConstantProductCurve, Curve, PoolCurve, StableSwapCurve,
+};
+use crate::graph::RoutingGraph;
+use crate::types::*;
+use anyhow::{Context, Result};
+use log::{info, warn};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// 从代币列表和池子列表文件构建路由图的加载器
+///
+/// 支持的输入格式：
+/// - Solana token-list 风格的代币列表 JSON
+/// - 池子列表 JSON 或 CSV
+///
+/// 无法解析的文件整体报错；单行数据不合法时跳过该行并记录在加载报告中。
+pub struct GraphLoader;
+
+/// 代币列表中的一项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenListEntry {
+    /// 代币 mint 地址
+    #[serde(alias = "mint")]
+    pub address: String,
+    /// 代币符号
+    pub symbol: String,
+    /// 代币小数位数
+    pub decimals: u8,
+    /// 是否为已验证的代币
+    #[serde(default)]
+    pub verified: bool,
+    /// 可用于查找该代币的别名
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+/// token-list 文件的顶层结构（也接受直接为数组的文件）
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TokenListFile {
+    List { tokens: Vec<serde_json::Value> },
+    Entries(Vec<serde_json::Value>),
+}
+
+/// 池子的定价曲线类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CurveType {
+    ConstantProduct,
+    StableSwap,
+    ConcentratedLiquidity,
+}
+
+/// 池子列表中的一项，描述 `mint_a` 与 `mint_b` 之间的一个池子
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolListEntry {
+    /// 池子标识
+    pub pool_id: String,
+    /// DEX 平台名称
+    pub dex: String,
+    /// DEX 平台地址
+    #[serde(default)]
+    pub dex_address: String,
+    /// 代币 A 的 mint 地址
+    pub mint_a: String,
+    /// 代币 B 的 mint 地址
+    pub mint_b: String,
+    /// 代币 A 的储备量（集中流动性池为活跃区间内的虚拟储备量）
+    pub reserve_a: Decimal,
+    /// 代币 B 的储备量（集中流动性池为活跃区间内的虚拟储备量）
+    pub reserve_b: Decimal,
+    /// 交易费用率，例如：0.003 表示 0.3%
+    pub fee_rate: Decimal,
+    /// 定价曲线类型
+    pub curve_type: CurveType,
+    /// StableSwap 放大系数
+    #[serde(default)]
+    pub amplification: Option<Decimal>,
+    /// 集中流动性区间下界价格（以 B/A 计）
+    #[serde(default)]
+    pub price_lower: Option<Decimal>,
+    /// 集中流动性区间上界价格（以 B/A 计）
+    #[serde(default)]
+    pub price_upper: Option<Decimal>,
+    /// A -> B 方向的最大单笔交易规模（以 A 计），默认为 A 的储备量
+    #[serde(default)]
+    pub max_trade_size: Option<Decimal>,
+    /// A -> B 方向的最小交易规模（以 A 计），默认为 0
+    #[serde(default)]
+    pub min_trade_size: Option<Decimal>,
+}
+
+/// 被跳过的一行数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RowError {
+    /// 行号（JSON 为数组中从 1 开始的序号，CSV 为文件中的行号）
+    pub row: usize,
+    /// 跳过原因
+    pub reason: String,
+}
+
+/// 一次加载的结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LoadReport {
+    /// 成功加载的代币数量
+    pub tokens_loaded: usize,
+    /// 成功加载的边数量（每个池子两条）
+    pub edges_loaded: usize,
+    /// 被跳过的行及原因
+    pub errors: Vec<RowError>,
+}
+
+impl LoadReport {
+    fn reject(&mut self, row: usize, reason: impl Into<String>) {
+        let reason = reason.into();
+        warn!("⚠️  跳过第 {} 行: {}", row, reason);
+        self.errors.push(RowError { row, reason });
+    }
+}
+
+impl GraphLoader {
+    /// 按文件扩展名加载代币列表文件（目前只支持 JSON）
+    ///
+    /// # 参数
+    /// * `graph` - 要写入的路由图
+    /// * `path` - 代币列表文件路径
+    ///
+    /// # 返回值
+    /// * `Result<LoadReport>` - 加载报告；文件无法读取或解析时返回错误
+    pub fn load_token_list_file(graph: &mut RoutingGraph, path: &Path) -> Result<LoadReport> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("无法读取代币列表文件 {}", path.display()))?;
+        Self::load_token_list_json(graph, &content)
+    }
+
+    /// 按文件扩展名加载池子列表文件（`.csv` 为 CSV，其余按 JSON 解析）
+    ///
+    /// # 参数
+    /// * `graph` - 要写入的路由图，池子引用的代币必须已经存在
+    /// * `path` - 池子列表文件路径
+    ///
+    /// # 返回值
+    /// * `Result<LoadReport>` - 加载报告；文件无法读取或解析时返回错误
+    pub fn load_pool_list_file(graph: &mut RoutingGraph, path: &Path) -> Result<LoadReport> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("无法读取池子列表文件 {}", path.display()))?;
+        let is_csv = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+        if is_csv {
+            Self::load_pool_list_csv(graph, &content)
+        } else {
+            Self::load_pool_list_json(graph, &content)
+        }
+    }
+
+    /// 加载 token-list 风格的代币列表 JSON
+    ///
+    /// # 参数
+    /// * `graph` - 要写入的路由图
+    /// * `json` - 形如 `{"tokens": [...]}` 或 `[...]` 的 JSON 文本
+    ///
+    /// # 返回值
+    /// * `Result<LoadReport>` - 加载报告
+    pub fn load_token_list_json(graph: &mut RoutingGraph, json: &str) -> Result<LoadReport> {
+        let entries = match serde_json::from_str::<TokenListFile>(json)
+            .context("代币列表 JSON 格式无效")?
+        {
+            TokenListFile::List { tokens } => tokens,
+            TokenListFile::Entries(entries) => entries,
+        };
+
+        let mut report = LoadReport::default();
+        let mut seen = HashSet::new();
+        for (index, value) in entries.into_iter().enumerate() {
+            let row = index + 1;
+            let entry = match serde_json::from_value::<TokenListEntry>(value) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    report.reject(row, format!("无法解析代币: {}", e));
+                    continue;
+                }
+            };
+            if let Err(reason) = Self::validate_token(&entry) {
+                report.reject(row, reason);
+                continue;
+            }
+            if !seen.insert(entry.address.clone()) {
+                report.reject(row, format!("重复的代币地址 {}", entry.address));
+                continue;
+            }
+
+            graph.upsert_token(Token {
+                symbol: entry.symbol,
+                address: entry.address.clone(),
+                decimals: entry.decimals,
+            });
+            graph.set_token_verified(&entry.address, entry.verified);
+            for alias in &entry.aliases {
+                graph.add_token_alias(&entry.address, alias);
+            }
+            report.tokens_loaded += 1;
+        }
+
+        info!(
+            "📥 加载了 {} 个代币，跳过 {} 行",
+            report.tokens_loaded,
+            report.errors.len()
+        );
+        Ok(report)
+    }
+
+    /// 加载池子列表 JSON（池子对象数组）
+    ///
+    /// # 参数
+    /// * `graph` - 要写入的路由图，池子引用的代币必须已经存在
+    /// * `json` - 池子对象数组的 JSON 文本
+    ///
+    /// # 返回值
+    /// * `Result<LoadReport>` - 加载报告
+    pub fn load_pool_list_json(graph: &mut RoutingGraph, json: &str) -> Result<LoadReport> {
+        let values: Vec<serde_json::Value> =
+            serde_json::from_str(json).context("池子列表 JSON 格式无效")?;
+
+        let rows = values.into_iter().enumerate().map(|(index, value)| {
+            let entry = serde_json::from_value::<PoolListEntry>(value)
+                .map_err(|e| format!("无法解析池子: {}", e));
+            (index + 1, entry)
+        });
+        Ok(Self::load_pools(graph, rows))
+    }
+
+    /// 加载带表头的池子列表 CSV，列名与 `PoolListEntry` 的字段名一致
+    ///
+    /// # 参数
+    /// * `graph` - 要写入的路由图，池子引用的代币必须已经存在
+    /// * `csv` - CSV 文本，可选列留空表示未指定
+    ///
+    /// # 返回值
+    /// * `Result<LoadReport>` - 加载报告
+    pub fn load_pool_list_csv(graph: &mut RoutingGraph, csv: &str) -> Result<LoadReport> {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(csv.as_bytes());
+        reader.headers().context("池子列表 CSV 缺少表头")?;
+
+        let rows: Vec<_> = reader
+            .deserialize::<PoolListEntry>()
+            .enumerate()
+            .map(|(index, result)| match result {
+                Ok(entry) => (index + 2, Ok(entry)),
+                Err(e) => {
+                    let row = e
+                        .position()
+                        .map(|position| position.line() as usize)
+                        .unwrap_or(index + 2);
+                    (row, Err(format!("无法解析池子: {}", e)))
+                }
+            })
+            .collect();
+        Ok(Self::load_pools(graph, rows))
+    }
+
+    /// 校验并写入已解析的池子行
+    fn load_pools(
+        graph: &mut RoutingGraph,
+        rows: impl IntoIterator<Item = (usize, Result<PoolListEntry, String>)>,
+    ) -> LoadReport {
+        let mut report = LoadReport::default();
+        let mut seen = HashSet::new();
+
+        for (row, entry) in rows {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(reason) => {
+                    report.reject(row, reason);
+                    continue;
+                }
+            };
+            if !seen.insert(entry.pool_id.clone()) {
+                report.reject(row, format!("重复的池子标识 {}", entry.pool_id));
+                continue;
+            }
+            match Self::build_edges(graph, &entry) {
+                Ok(edges) => {
+                    for edge in edges {
+                        graph.upsert_edge(edge);
+                        report.edges_loaded += 1;
+                    }
+                }
+                Err(reason) => report.reject(row, reason),
+            }
+        }
+
+        info!(
+            "📥 加载了 {} 条边，跳过 {} 行",
+            report.edges_loaded,
+            report.errors.len()
+        );
+        report
+    }
+
+    /// 校验代币列表中的一项
+    fn validate_token(entry: &TokenListEntry) -> Result<(), String> {
+        if entry.address.trim().is_empty() {
+            return Err("代币地址不能为空".to_string());
+        }
+        if entry.symbol.trim().is_empty() {
+            return Err(format!("代币 {} 的符号不能为空", entry.address));
+        }
+        if entry.decimals > MAX_TOKEN_DECIMALS {
+            return Err(format!(
+                "代币 {} 的小数位数 {} 超过 {}",
+                entry.symbol, entry.decimals, MAX_TOKEN_DECIMALS
+            ));
+        }
+        Ok(())
+    }
+
+    /// 校验池子行并构建 A -> B 和 B -> A 两个方向的边
+    fn build_edges(graph: &RoutingGraph, entry: &PoolListEntry) -> Result<[Edge; 2], String> {
+        if entry.pool_id.trim().is_empty() {
+            return Err("池子标识不能为空".to_string());
+        }
+        if entry.dex.trim().is_empty() {
+            return Err(format!("池子 {} 的 DEX 名称不能为空", entry.pool_id));
+        }
+        if entry.mint_a == entry.mint_b {
+            return Err(format!("池子 {} 的两侧代币相同", entry.pool_id));
+        }
+        let token_a = graph
+            .nodes
+            .get(&entry.mint_a)
+            .ok_or_else(|| format!("池子 {} 引用了未知代币 {}", entry.pool_id, entry.mint_a))?;
+        let token_b = graph
+            .nodes
+            .get(&entry.mint_b)
+            .ok_or_else(|| format!("池子 {} 引用了未知代币 {}", entry.pool_id, entry.mint_b))?;
+        if entry.reserve_a <= dec!(0) || entry.reserve_b <= dec!(0) {
+            return Err(format!("池子 {} 的储备量必须为正数", entry.pool_id));
+        }
+        if entry.fee_rate < dec!(0) || entry.fee_rate >= dec!(1) {
+            return Err(format!("池子 {} 的费率必须在 [0, 1) 之间", entry.pool_id));
+        }
+
+        let curve = Self::build_curve(entry)?;
+
+        let max_trade_size = entry.max_trade_size.unwrap_or(entry.reserve_a);
+        let min_trade_size = entry.min_trade_size.unwrap_or(dec!(0));
+        if min_trade_size < dec!(0) || max_trade_size <= min_trade_size {
+            return Err(format!("池子 {} 的交易规模范围无效", entry.pool_id));
+        }
+
+        let dex_platform = DexPlatform {
+            name: entry.dex.clone(),
+            address: entry.dex_address.clone(),
+            fee_rate: entry.fee_rate,
+        };
+
+        // 反方向的交易规模按现货价格折算为以 B 计
+        let spot_price = curve.spot_price();
+        let reversed = curve.reversed();
+        Ok([
+            Edge::new(
+                entry.pool_id.clone(),
+                token_a.clone(),
+                token_b.clone(),
+                dex_platform.clone(),
+                curve,
+                max_trade_size,
+                min_trade_size,
+            ),
+            Edge::new(
+                entry.pool_id.clone(),
+                token_b.clone(),
+                token_a.clone(),
+                dex_platform,
+                reversed,
+                max_trade_size * spot_price,
+                min_trade_size * spot_price,
+            ),
+        ])
+    }
+
+    /// 由池子行构建 A -> B 方向的定价曲线
+    fn build_curve(entry: &PoolListEntry) -> Result<Curve, String> {
+        match entry.curve_type {
+            CurveType::ConstantProduct => Ok(Curve::ConstantProduct(ConstantProductCurve::new(
+                entry.reserve_a,
+                entry.reserve_b,
+            ))),
+            CurveType::StableSwap => {
+                let amplification = entry
+                    .amplification
+                    .filter(|amplification| *amplification > dec!(0))
+                    .ok_or_else(|| {
+                        format!("StableSwap 池子 {} 需要正的放大系数", entry.pool_id)
+                    })?;
+                Ok(Curve::StableSwap(StableSwapCurve::new(
+                    entry.reserve_a,
+                    entry.reserve_b,
+                    amplification,
+                )))
+            }
+            CurveType::ConcentratedLiquidity => {
+                let (Some(price_lower), Some(price_upper)) = (entry.price_lower, entry.price_upper)
+                else {
+                    return Err(format!(
+                        "集中流动性池子 {} 需要价格区间上下界",
+                        entry.pool_id
+                    ));
+                };
+                let price = entry.reserve_b / entry.reserve_a;
+                if price_lower <= dec!(0) || price < price_lower || price > price_upper {
+                    return Err(format!(
+                        "集中流动性池子 {} 的当前价格 {} 不在区间 [{}, {}] 内",
+                        entry.pool_id, price, price_lower, price_upper
+                    ));
+                }
+                Ok(Curve::ConcentratedLiquidity(
+                    ConcentratedLiquidityCurve::from_rate(
+                        entry.reserve_a,
+                        price,
+                        price_lower,
+                        price_upper,
+                    ),
+                ))
+            }
+        }
+    }
+}
+
+/// 代币允许的最大小数位数
+const MAX_TOKEN_DECIMALS: u8 = 18;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "pool_id,dex,dex_address,mint_a,mint_b,reserve_a,reserve_b,fee_rate,curve_type,amplification,price_lower,price_upper,max_trade_size,min_trade_size";
+
+    fn graph_with_tokens() -> RoutingGraph {
+        let mut graph = RoutingGraph::new(RouterConfig::default());
+        let report = GraphLoader::load_token_list_json(
+            &mut graph,
+            r#"{"tokens": [
+                {"address": "usdc-mint", "symbol": "USDC", "decimals": 6},
+                {"address": "sol-mint", "symbol": "SOL", "decimals": 9}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(report.tokens_loaded, 2);
+        graph
+    }
+
+    fn load_csv(rows: &[&str]) -> (RoutingGraph, LoadReport) {
+        let mut graph = graph_with_tokens();
+        let csv = std::iter::once(HEADER)
+            .chain(rows.iter().copied())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let report = GraphLoader::load_pool_list_csv(&mut graph, &csv).unwrap();
+        (graph, report)
+    }
+
+    fn assert_rejected(report: &LoadReport, row: usize, reason: &str) {
+        assert_eq!(report.errors.len(), 1, "{:?}", report.errors);
+        assert_eq!(report.errors[0].row, row);
+        assert!(
+            report.errors[0].reason.contains(reason),
+            "{}",
+            report.errors[0].reason
+        );
+    }
+
+    #[test]
+    fn valid_pool_builds_both_directions() {
+        let (graph, report) = load_csv(&[
+            "usdc-sol,Raydium,ray,usdc-mint,sol-mint,1000000,1000,0.0025,constant_product,,,,500000,10",
+        ]);
+
+        assert!(report.errors.is_empty());
+        assert_eq!(report.edges_loaded, 2);
+
+        let forward = graph.get_edge("usdc-sol", "usdc-mint").unwrap();
+        assert_eq!(forward.to_token.address, "sol-mint");
+        assert_eq!(forward.max_trade_size, dec!(500000));
+        assert_eq!(forward.min_trade_size, dec!(10));
+
+        // 反方向以 SOL 计：价格 1000 / 1000000 = 0.001
+        let reverse = graph.get_edge("usdc-sol", "sol-mint").unwrap();
+        assert_eq!(reverse.to_token.address, "usdc-mint");
+        assert_eq!(reverse.max_trade_size, dec!(500));
+        assert_eq!(reverse.min_trade_size, dec!(0.01));
+    }
+
+    #[test]
+    fn malformed_row_reports_its_line() {
+        let (graph, report) = load_csv(&[
+            "usdc-sol,Raydium,ray,usdc-mint,sol-mint,1000000,1000,0.0025,constant_product,,,,,",
+            "bad-row,Orca,orca,usdc-mint,sol-mint,not-a-number,1000,0.003,constant_product,,,,,",
+        ]);
+
+        assert_rejected(&report, 3, "无法解析池子");
+        assert_eq!(report.edges_loaded, 2);
+        assert!(graph.get_edge("bad-row", "usdc-mint").is_none());
+    }
+
+    #[test]
+    fn unknown_mint_is_rejected() {
+        let (_, report) = load_csv(&[
+            "usdc-bonk,Raydium,ray,usdc-mint,bonk-mint,1000000,1000,0.0025,constant_product,,,,,",
+        ]);
+
+        assert_rejected(&report, 2, "未知代币 bonk-mint");
+        assert_eq!(report.edges_loaded, 0);
+    }
+
+    #[test]
+    fn duplicate_pool_id_is_rejected() {
+        let (graph, report) = load_csv(&[
+            "usdc-sol,Raydium,ray,usdc-mint,sol-mint,1000000,1000,0.0025,constant_product,,,,,",
+            "usdc-sol,Orca,orca,usdc-mint,sol-mint,2000000,2000,0.003,constant_product,,,,,",
+        ]);
+
+        assert_rejected(&report, 3, "重复的池子标识 usdc-sol");
+        assert_eq!(report.edges_loaded, 2);
+        let edge = graph.get_edge("usdc-sol", "usdc-mint").unwrap();
+        assert_eq!(edge.dex_platform.name, "Raydium");
+    }
+
+    #[test]
+    fn non_positive_reserves_are_rejected() {
+        for reserve in ["0", "-5"] {
+            let row = format!(
+                "usdc-sol,Raydium,ray,usdc-mint,sol-mint,1000000,{},0.0025,constant_product,,,,,",
+                reserve
+            );
+            let (_, report) = load_csv(&[&row]);
+
+            assert_rejected(&report, 2, "储备量必须为正数");
+        }
+    }
+
+    #[test]
+    fn fee_of_one_or_more_is_rejected() {
+        for fee in ["1", "1.5"] {
+            let row = format!(
+                "usdc-sol,Raydium,ray,usdc-mint,sol-mint,1000000,1000,{},constant_product,,,,,",
+                fee
+            );
+            let (_, report) = load_csv(&[&row]);
+
+            assert_rejected(&report, 2, "费率必须在 [0, 1) 之间");
+        }
+    }
+
+    #[test]
+    fn clmm_price_outside_range_is_rejected() {
+        let (_, report) = load_csv(&[
+            "orca-usdc-sol,Orca,orca,usdc-mint,sol-mint,500000,505,0.003,concentrated_liquidity,,0.0008,0.001,,",
+        ]);
+
+        assert_rejected(&report, 2, "不在区间");
+    }
+}
@@ -1,66 +1,342 @@
-mod types;
-mod graph;
-mod routing;
-mod quote;
-mod utils;
-
 use anyhow::Result;
-use log::{info, warn};
-use types::*;
-use routing::MetisRouter;
+use clap::{Args, Parser, Subcommand};
+use log::info;
+use metis::loader::GraphLoader;
+use metis::routing::MetisRouter;
+use metis::types::*;
+use metis::utils::FormatUtils;
+use rust_decimal::Decimal;
+use std::path::PathBuf;
+
+/// Metis DEX 聚合路由器命令行工具
+#[derive(Debug, Parser)]
+#[command(name = "metis", version, about)]
+struct Cli {
+    /// 代币列表文件（JSON），需与 --pools 同时指定；未指定时使用示例数据
+    #[arg(long, global = true, requires = "pools")]
+    tokens: Option<PathBuf>,
+
+    /// 池子列表文件（JSON 或 CSV），需与 --tokens 同时指定
+    #[arg(long, global = true, requires = "tokens")]
+    pools: Option<PathBuf>,
+
+    /// 以 JSON 格式输出结果
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// 寻找最优路由
+    Route(RouteArgs),
+    /// 并行获取多个 DEX 平台的报价
+    Quote(QuoteArgs),
+    /// 查看路由图
+    #[command(subcommand)]
+    Graph(GraphCommand),
+    /// 寻找最优路由并分析其性能
+    Analyze(RouteArgs),
+}
+
+#[derive(Debug, Args)]
+struct RouteArgs {
+    /// 输入代币（符号、别名或 mint 地址）
+    #[arg(long)]
+    input: String,
+
+    /// 输出代币（符号、别名或 mint 地址）
+    #[arg(long)]
+    output: String,
+
+    /// 交易数量；指定 --exact-out 时为期望的输出数量
+    #[arg(long)]
+    amount: Decimal,
+
+    /// 滑点容差（0-1 之间的小数）
+    #[arg(long, default_value = "0.005")]
+    slippage: Decimal,
+
+    /// 固定输出数量，反向搜索所需输入最少的路由
+    #[arg(long, conflicts_with = "split")]
+    exact_out: bool,
+
+    /// 启用分割路由
+    #[arg(long)]
+    split: bool,
+
+    /// 分割路由的最大路径数
+    #[arg(long, requires = "split")]
+    max_splits: Option<usize>,
+
+    /// 最大跳数
+    #[arg(long)]
+    max_hops: Option<usize>,
+
+    /// 备选路由数量
+    #[arg(long)]
+    alternatives: Option<usize>,
+}
+
+impl RouteArgs {
+    fn to_request(&self) -> RouteRequest {
+        let (swap_mode, output_amount) = if self.exact_out {
+            (SwapMode::ExactOut, Some(self.amount))
+        } else {
+            (SwapMode::ExactIn, None)
+        };
+
+        RouteRequest {
+            input_token: self.input.clone(),
+            output_token: self.output.clone(),
+            input_amount: if self.exact_out {
+                Decimal::ZERO
+            } else {
+                self.amount
+            },
+            swap_mode,
+            output_amount,
+            slippage_tolerance: self.slippage,
+            max_iterations: 5,
+            max_hops: self.max_hops,
+            enable_split_routes: self.split,
+            max_splits: self.max_splits,
+            max_alternatives: self.alternatives,
+            filters: RouteFilters::default(),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+struct QuoteArgs {
+    /// 输入代币
+    #[arg(long)]
+    input: String,
+
+    /// 输出代币
+    #[arg(long)]
+    output: String,
+
+    /// 输入数量
+    #[arg(long)]
+    amount: Decimal,
+
+    /// 要询价的 DEX 平台，可重复指定
+    #[arg(long = "dex", default_values = ["Raydium", "Orca", "Meteora"])]
+    dexes: Vec<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum GraphCommand {
+    /// 列出所有代币
+    ListTokens,
+    /// 列出所有边，可按源代币过滤
+    ListEdges {
+        /// 只列出从该代币出发的边
+        #[arg(long)]
+        from: Option<String>,
+    },
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
     env_logger::init();
-    
-    info!("🚀 启动 Metis DEX 聚合路由器");
-    
-    // Metis 路由算法的使用示例
+
+    let cli = Cli::parse();
+    let router = build_router(&cli)?;
+
+    match &cli.command {
+        Command::Route(args) => run_route(&router, args, cli.json).await?,
+        Command::Quote(args) => run_quote(&router, args, cli.json).await?,
+        Command::Graph(command) => run_graph(&router, command, cli.json)?,
+        Command::Analyze(args) => run_analyze(&router, args, cli.json).await?,
+    }
+
+    // 设置 METIS_METRICS_ADDR（如 127.0.0.1:9100）时，命令完成后继续提供 Prometheus 指标端点
+    #[cfg(feature = "metrics-exporter")]
+    if let Ok(addr) = std::env::var("METIS_METRICS_ADDR") {
+        metis::exporter::serve_metrics(std::sync::Arc::new(router), addr.parse()?).await?;
+    }
+
+    Ok(())
+}
+
+/// 用数据文件或示例数据构建路由器
+fn build_router(cli: &Cli) -> Result<MetisRouter> {
     let mut router = MetisRouter::new();
-    router.initialize();
-    
-    // 示例：用 1000 USDC 寻找从 USDC 到 SOL 的最优路由
-    let request = RouteRequest {
-        input_token: "USDC".to_string(),
-        output_token: "SOL".to_string(),
-        input_amount: rust_decimal_macros::dec!(1000.0),
-        slippage_tolerance: rust_decimal_macros::dec!(0.005), // 0.5%
-        max_iterations: 5,
-        enable_split_routes: true,
-        max_splits: Some(3),
+    match (&cli.tokens, &cli.pools) {
+        (Some(tokens), Some(pools)) => {
+            let graph = router.graph_mut();
+            let token_report = GraphLoader::load_token_list_file(graph, tokens)?;
+            let pool_report = GraphLoader::load_pool_list_file(graph, pools)?;
+            info!(
+                "📂 已加载 {} 个代币和 {} 条边（跳过 {} 行）",
+                token_report.tokens_loaded,
+                pool_report.edges_loaded,
+                token_report.errors.len() + pool_report.errors.len()
+            );
+        }
+        _ => router.initialize(),
+    }
+    // 演示用的模拟报价提供者按已加载的池子构造
+    router.register_mock_providers();
+    Ok(router)
+}
+
+fn print_json<T: serde::Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+async fn run_route(router: &MetisRouter, args: &RouteArgs, json: bool) -> Result<()> {
+    let response = router.find_optimal_route(args.to_request()).await?;
+    if json {
+        return print_json(&response);
+    }
+
+    match (&response.route, &response.split_route) {
+        (Some(route), _) => println!("{}", FormatUtils::format_route_summary(route)),
+        (None, Some(split_route)) => {
+            println!("{}", FormatUtils::format_split_route_summary(split_route))
+        }
+        (None, None) => println!("❌ 未找到路由"),
+    }
+    if let Some(max_input) = response.max_input_amount {
+        println!(
+            "最大输入（含滑点）: {}",
+            FormatUtils::format_currency(max_input, &args.input)
+        );
+    }
+    for (i, route) in response.alternatives.iter().enumerate() {
+        println!(
+            "\n备选路由 {}:\n{}",
+            i + 1,
+            FormatUtils::format_route_summary(route)
+        );
+    }
+    println!(
+        "\n耗时: {}ms, 迭代次数: {}",
+        response.execution_time_ms, response.iterations_used
+    );
+    Ok(())
+}
+
+async fn run_quote(router: &MetisRouter, args: &QuoteArgs, json: bool) -> Result<()> {
+    let request = QuoteRequest {
+        input_token: args.input.clone(),
+        output_token: args.output.clone(),
+        amount: args.amount,
+        dex_platform: String::new(),
+        pool_id: None,
+        input_mint: None,
+        output_mint: None,
     };
-    
-    match router.find_optimal_route(request).await {
-        Ok(response) => {
-            if response.route.is_some() || response.split_route.is_some() {
-                info!("✅ 找到最优路由:");
-                println!("{}", serde_json::to_string_pretty(&response)?);
-                
-                // 如果找到路由，显示额外分析
-                if let Some(route) = &response.route {
-                    let analysis = router.analyze_route(route);
-                    println!("\n📊 路由分析:");
-                    println!("总跳数: {}", analysis.total_hops);
-                    println!("平均价格影响: {:.2}%", analysis.avg_price_impact * rust_decimal_macros::dec!(100));
-                    println!("总费用: {:.6} USDC", analysis.total_fees);
-                    println!("效率分数: {:.2}", analysis.efficiency_score);
-                    
-                    if !analysis.recommendations.is_empty() {
-                        println!("\n💡 建议:");
-                        for rec in &analysis.recommendations {
-                            println!("- {}", rec);
-                        }
-                    }
-                }
-            } else {
-                warn!("❌ 未找到路由");
-                println!("{}", serde_json::to_string_pretty(&response)?);
+    let quotes = router.get_multi_dex_quotes(&request, &args.dexes).await?;
+
+    if json {
+        let quotes: Vec<serde_json::Value> = quotes
+            .iter()
+            .map(|(dex, quote)| serde_json::json!({ "dex_platform": dex, "quote": quote }))
+            .collect();
+        return print_json(&quotes);
+    }
+
+    if quotes.is_empty() {
+        println!("❌ 没有 DEX 平台返回报价");
+    }
+    for (dex, quote) in &quotes {
+        let request = QuoteRequest {
+            dex_platform: dex.clone(),
+            ..request.clone()
+        };
+        println!(
+            "{}",
+            FormatUtils::format_quote_summary(dex, &request, quote)
+        );
+    }
+    Ok(())
+}
+
+fn run_graph(router: &MetisRouter, command: &GraphCommand, json: bool) -> Result<()> {
+    let graph = router.graph();
+    match command {
+        GraphCommand::ListTokens => {
+            let mut tokens: Vec<_> = graph.token_registry().records().collect();
+            tokens.sort_by(|a, b| a.token.symbol.cmp(&b.token.symbol));
+            if json {
+                return print_json(&tokens);
+            }
+            for record in tokens {
+                println!("{}", FormatUtils::format_token_summary(record));
             }
         }
-        Err(e) => {
-            eprintln!("❌ 寻找路由失败: {}", e);
+        GraphCommand::ListEdges { from } => {
+            let from_addr = from
+                .as_deref()
+                .map(|query| {
+                    graph
+                        .resolve_token(query)
+                        .map(|token| token.address.clone())
+                })
+                .transpose()?;
+            let mut edges: Vec<&Edge> = graph
+                .edges
+                .values()
+                .flatten()
+                .filter(|edge| {
+                    from_addr
+                        .as_ref()
+                        .is_none_or(|addr| edge.from_token.address == *addr)
+                })
+                .collect();
+            edges.sort_by(|a, b| {
+                (&a.from_token.symbol, &a.to_token.symbol, &a.pool_id).cmp(&(
+                    &b.from_token.symbol,
+                    &b.to_token.symbol,
+                    &b.pool_id,
+                ))
+            });
+            if json {
+                return print_json(&edges);
+            }
+            for edge in edges {
+                println!("{}", FormatUtils::format_edge_summary(edge));
+            }
         }
     }
-    
+    Ok(())
+}
+
+async fn run_analyze(router: &MetisRouter, args: &RouteArgs, json: bool) -> Result<()> {
+    let response = router.find_optimal_route(args.to_request()).await?;
+    // 分割路由按各条子路由分别分析
+    let routes: Vec<&Route> = match (&response.route, &response.split_route) {
+        (Some(route), _) => vec![route],
+        (None, Some(split_route)) => split_route.routes.iter().collect(),
+        (None, None) => Vec::new(),
+    };
+
+    if json {
+        let analyses: Vec<serde_json::Value> = routes
+            .iter()
+            .map(|route| {
+                serde_json::json!({ "route": route, "analysis": router.analyze_route(route) })
+            })
+            .collect();
+        return print_json(&analyses);
+    }
+
+    if routes.is_empty() {
+        println!("❌ 未找到路由");
+    }
+    for route in routes {
+        println!("{}", FormatUtils::format_route_summary(route));
+        println!(
+            "\n📊 路由分析:\n{}\n",
+            FormatUtils::format_route_analysis(&router.analyze_route(route))
+        );
+    }
     Ok(())
 }
@@ -0,0 +1,296 @@
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// 路由延迟直方图的桶上界（毫秒）
+pub const LATENCY_BUCKETS_MS: &[f64] = &[
+    1.0, 2.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0,
+];
+
+/// Bellman-Ford 迭代次数直方图的桶上界
+pub const ITERATION_BUCKETS: &[f64] = &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 8.0, 10.0];
+
+/// 可并发记录的固定桶直方图
+#[derive(Debug)]
+pub struct Histogram {
+    /// 各桶的上界，超过最后一个上界的样本计入 +Inf 桶
+    bounds: &'static [f64],
+    /// 每个桶内（不累计）的样本数，最后一个元素为 +Inf 桶
+    buckets: Vec<AtomicU64>,
+    /// 样本总数
+    count: AtomicU64,
+    /// 样本总和（以千分之一单位存储，避免浮点原子操作）
+    sum_milli: AtomicU64,
+}
+
+/// 直方图的快照
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HistogramSnapshot {
+    /// (桶上界, 小于等于该上界的累计样本数)，不含 +Inf 桶
+    pub buckets: Vec<(f64, u64)>,
+    /// 样本总数
+    pub count: u64,
+    /// 样本总和
+    pub sum: f64,
+}
+
+impl HistogramSnapshot {
+    /// 平均值，没有样本时为 0
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.sum / self.count as f64
+        }
+    }
+}
+
+impl Histogram {
+    pub fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            buckets: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum_milli: AtomicU64::new(0),
+        }
+    }
+
+    /// 记录一个样本
+    pub fn observe(&self, value: f64) {
+        let index = self
+            .bounds
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(self.bounds.len());
+        self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_milli
+            .fetch_add((value.max(0.0) * 1000.0).round() as u64, Ordering::Relaxed);
+    }
+
+    /// 以毫秒为单位记录一个耗时样本
+    pub fn observe_duration(&self, elapsed: Duration) {
+        self.observe(elapsed.as_secs_f64() * 1000.0);
+    }
+
+    /// 获取累计形式的快照
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let mut cumulative = 0;
+        let buckets = self
+            .bounds
+            .iter()
+            .zip(&self.buckets)
+            .map(|(bound, count)| {
+                cumulative += count.load(Ordering::Relaxed);
+                (*bound, cumulative)
+            })
+            .collect();
+        HistogramSnapshot {
+            buckets,
+            count: self.count.load(Ordering::Relaxed),
+            sum: self.sum_milli.load(Ordering::Relaxed) as f64 / 1000.0,
+        }
+    }
+}
+
+/// 路由请求未返回路由的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NoRouteReason {
+    /// 请求参数无效
+    InvalidRequest,
+    /// 输入或输出代币不存在
+    UnknownToken,
+    /// 代币符号对应多个代币
+    AmbiguousToken,
+    /// 跳数和过滤条件内没有可用路径
+    NoPath,
+    /// 搜索过程中出错
+    SearchFailed,
+}
+
+impl NoRouteReason {
+    /// 用作指标标签的名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NoRouteReason::InvalidRequest => "invalid_request",
+            NoRouteReason::UnknownToken => "unknown_token",
+            NoRouteReason::AmbiguousToken => "ambiguous_token",
+            NoRouteReason::NoPath => "no_path",
+            NoRouteReason::SearchFailed => "search_failed",
+        }
+    }
+}
+
+/// 单个交易对的路由请求统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PairStats {
+    /// 请求总数
+    pub requests: u64,
+    /// 找到路由的请求数
+    pub successes: u64,
+}
+
+impl PairStats {
+    /// 成功率（0-1 之间的小数），没有请求时为 0
+    pub fn success_rate(&self) -> f64 {
+        ratio(self.successes, self.requests)
+    }
+}
+
+/// 缓存命中计数
+#[derive(Debug, Default)]
+pub struct HitCounter {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl HitCounter {
+    /// 记录一次命中
+    pub fn hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录一次未命中
+    pub fn miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 命中次数
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// 未命中次数
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// 命中率（0-1 之间的小数），没有查找时为 0
+    pub fn hit_rate(&self) -> f64 {
+        let hits = self.hits();
+        ratio(hits, hits + self.misses())
+    }
+}
+
+/// 路由器的运行指标
+#[derive(Debug)]
+pub struct RouterMetrics {
+    /// 路由缓存命中计数
+    pub route_cache: HitCounter,
+    /// 路由请求延迟（毫秒）
+    pub route_latency: Histogram,
+    /// 每次实际搜索使用的迭代次数（缓存命中不计入）
+    pub iterations: Histogram,
+    /// 交易对 -> 请求统计
+    pairs: DashMap<String, PairStats>,
+    /// 未返回路由的原因 -> 次数
+    no_route: DashMap<NoRouteReason, u64>,
+}
+
+impl RouterMetrics {
+    pub fn new() -> Self {
+        Self {
+            route_cache: HitCounter::default(),
+            route_latency: Histogram::new(LATENCY_BUCKETS_MS),
+            iterations: Histogram::new(ITERATION_BUCKETS),
+            pairs: DashMap::new(),
+            no_route: DashMap::new(),
+        }
+    }
+
+    /// 记录一次通过校验的路由请求的结果和耗时，交易对按输入和输出代币的 mint 地址建键
+    pub fn record_route(
+        &self,
+        input_mint: &str,
+        output_mint: &str,
+        success: bool,
+        elapsed: Duration,
+    ) {
+        self.route_latency.observe_duration(elapsed);
+        let mut pair = self
+            .pairs
+            .entry(format!("{}->{}", input_mint, output_mint))
+            .or_default();
+        pair.requests += 1;
+        if success {
+            pair.successes += 1;
+        }
+    }
+
+    /// 记录一次未返回路由的请求
+    pub fn record_no_route(&self, reason: NoRouteReason) {
+        *self.no_route.entry(reason).or_default() += 1;
+    }
+
+    /// 各交易对的请求统计
+    pub fn pair_stats(&self) -> BTreeMap<String, PairStats> {
+        self.pairs
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect()
+    }
+
+    /// 按原因统计的未返回路由次数
+    pub fn no_route_by_reason(&self) -> BTreeMap<String, u64> {
+        self.no_route
+            .iter()
+            .map(|entry| (entry.key().as_str().to_string(), *entry.value()))
+            .collect()
+    }
+}
+
+impl Default for RouterMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 报价服务的运行指标
+#[derive(Debug, Default)]
+pub struct QuoteMetrics {
+    /// 报价缓存命中计数
+    pub cache: HitCounter,
+    /// DEX 平台 -> 报价失败次数
+    errors: DashMap<String, u64>,
+    /// 合并到进行中请求、等待其结果的调用次数
+    coalesced: AtomicU64,
+}
+
+impl QuoteMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一次合并到进行中请求的等待
+    pub fn record_coalesced(&self) {
+        self.coalesced.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 合并到进行中请求的等待次数
+    pub fn coalesced(&self) -> u64 {
+        self.coalesced.load(Ordering::Relaxed)
+    }
+
+    /// 记录一次 DEX 报价失败
+    pub fn record_error(&self, dex_platform: &str) {
+        *self.errors.entry(dex_platform.to_string()).or_default() += 1;
+    }
+
+    /// 各 DEX 平台的报价失败次数
+    pub fn errors_by_dex(&self) -> BTreeMap<String, u64> {
+        self.errors
+            .iter()
+            .map(|entry| (entry.key().clone(), *entry.value()))
+            .collect()
+    }
+}
+
+fn ratio(numerator: u64, denominator: u64) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
@@ -0,0 +1,515 @@
+use crate::curve::{ConstantProductCurve, PoolCurve};
+use crate::types::*;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use log::debug;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// 单个 DEX 平台的报价来源
+///
+/// 在 `QuoteService` 中按 DEX 平台名称注册，接入新的交易场所只需实现此 trait。
+#[async_trait]
+pub trait QuoteProvider: Send + Sync {
+    /// 提供者对应的 DEX 平台名称，如 "Raydium"
+    fn dex_platform(&self) -> &str;
+
+    /// 获取报价，返回的输出数量应已扣除交易费用
+    ///
+    /// 请求指定了 `pool_id` 时必须按该池子报价，不能用同一交易对的其他池子代替；
+    /// 指定了 `input_mint`/`output_mint` 时按 mint 而不是符号识别代币。
+    async fn fetch_quote(&self, request: &QuoteRequest) -> Result<QuoteResponse>;
+
+    /// 提供者是否能为该请求报价，默认认为都能
+    ///
+    /// 市场数据刷新会跳过不支持的池子和交易对，而不是把它们计为该 DEX 的失败。
+    fn supports(&self, _request: &QuoteRequest) -> bool {
+        true
+    }
+}
+
+/// 重试也无法成功的报价错误
+///
+/// 表示请求本身无法报价，而不是交易场所暂时不可用；`QuoteService` 不会重试这类错误，
+/// 也不把它计入该 DEX 的熔断失败。提供者遇到其余错误时照常返回任意 `anyhow::Error`。
+#[derive(Debug, Clone, Error)]
+pub enum QuoteRejection {
+    /// 提供者没有请求的交易对或池子方向
+    #[error("{0}")]
+    Unsupported(String),
+    /// 池子流动性不足以承接请求的数量
+    #[error("{0}")]
+    InsufficientLiquidity(String),
+    /// 报价接口以客户端错误拒绝了请求（HTTP 4xx，408 和 429 除外）
+    #[error("{dex_platform} 报价接口拒绝了请求: HTTP {status}")]
+    Rejected {
+        /// 拒绝请求的 DEX 平台
+        dex_platform: String,
+        /// HTTP 状态码
+        status: u16,
+    },
+}
+
+impl QuoteRejection {
+    /// 错误链中是否包含 `QuoteRejection`，即重试无意义的错误
+    pub fn is_rejection(error: &anyhow::Error) -> bool {
+        error.chain().any(|cause| cause.is::<QuoteRejection>())
+    }
+}
+
+/// 模拟报价提供者的单个交易对参数
+#[derive(Debug, Clone)]
+pub struct MockPool {
+    /// 现货汇率（1 个输入代币可兑换的输出代币数量）
+    pub exchange_rate: Decimal,
+    /// 输入代币一侧的流动性
+    pub liquidity: Decimal,
+    /// 交易费用率
+    pub fee_rate: Decimal,
+}
+
+/// 确定性的内存报价提供者
+///
+/// 每个交易对或池子按恒定乘积曲线计算报价，不发起网络请求，相同输入总是得到相同输出；
+/// 未配置的交易对或池子返回错误。代币可以用符号或 mint 地址配置。
+#[derive(Debug, Clone)]
+pub struct MockQuoteProvider {
+    dex_platform: String,
+    /// (输入代币, 输出代币) -> 池子参数，用于未指定池子的请求
+    pairs: HashMap<(String, String), MockPool>,
+    /// (池子标识, 输入代币) -> 池子参数，用于指定了池子的请求
+    pools: HashMap<(String, String), MockPool>,
+}
+
+impl MockQuoteProvider {
+    pub fn new(dex_platform: impl Into<String>) -> Self {
+        Self {
+            dex_platform: dex_platform.into(),
+            pairs: HashMap::new(),
+            pools: HashMap::new(),
+        }
+    }
+
+    /// 添加一个单向交易对
+    pub fn with_pair(
+        mut self,
+        input_token: impl Into<String>,
+        output_token: impl Into<String>,
+        exchange_rate: Decimal,
+        liquidity: Decimal,
+        fee_rate: Decimal,
+    ) -> Self {
+        self.pairs.insert(
+            (input_token.into(), output_token.into()),
+            MockPool {
+                exchange_rate,
+                liquidity,
+                fee_rate,
+            },
+        );
+        self
+    }
+
+    /// 添加一个池子在某个输入方向上的参数，同时作为该交易对的默认池子
+    ///
+    /// 同一交易对有多个池子时，未指定池子的请求使用最后添加的那个。
+    pub fn with_pool(
+        mut self,
+        pool_id: impl Into<String>,
+        input_token: impl Into<String>,
+        output_token: impl Into<String>,
+        exchange_rate: Decimal,
+        liquidity: Decimal,
+        fee_rate: Decimal,
+    ) -> Self {
+        let input_token = input_token.into();
+        let pool = MockPool {
+            exchange_rate,
+            liquidity,
+            fee_rate,
+        };
+        self.pools
+            .insert((pool_id.into(), input_token.clone()), pool.clone());
+        self.pairs.insert((input_token, output_token.into()), pool);
+        self
+    }
+
+    /// 查找请求对应的池子：指定了池子时只按池子查找，否则按交易对查找；mint 优先于符号
+    fn find_pool(&self, request: &QuoteRequest) -> Option<&MockPool> {
+        let inputs = [request.input_mint.as_ref(), Some(&request.input_token)];
+        let outputs = [request.output_mint.as_ref(), Some(&request.output_token)];
+        match &request.pool_id {
+            Some(pool_id) => inputs
+                .into_iter()
+                .flatten()
+                .find_map(|input| self.pools.get(&(pool_id.clone(), input.clone()))),
+            None => inputs.into_iter().flatten().find_map(|input| {
+                outputs
+                    .into_iter()
+                    .flatten()
+                    .find_map(|output| self.pairs.get(&(input.clone(), output.clone())))
+            }),
+        }
+    }
+
+    /// 由路由图中属于该 DEX 平台的边构造报价提供者（用于演示和测试）
+    ///
+    /// 每条边按其池子、输入和输出代币的 mint 注册，报价使用边当前的汇率、流动性和费率。
+    pub fn from_edges<'a>(
+        dex_platform: impl Into<String>,
+        edges: impl IntoIterator<Item = &'a Edge>,
+    ) -> Self {
+        let provider = Self::new(dex_platform);
+        let dex_platform = provider.dex_platform.clone();
+        edges
+            .into_iter()
+            .filter(|edge| edge.dex_platform.name == dex_platform)
+            .fold(provider, |provider, edge| {
+                provider.with_pool(
+                    edge.pool_id.clone(),
+                    edge.from_token.address.clone(),
+                    edge.to_token.address.clone(),
+                    edge.exchange_rate,
+                    edge.liquidity,
+                    edge.dex_platform.fee_rate,
+                )
+            })
+    }
+}
+
+#[async_trait]
+impl QuoteProvider for MockQuoteProvider {
+    fn dex_platform(&self) -> &str {
+        &self.dex_platform
+    }
+
+    fn supports(&self, request: &QuoteRequest) -> bool {
+        self.find_pool(request).is_some()
+    }
+
+    async fn fetch_quote(&self, request: &QuoteRequest) -> Result<QuoteResponse> {
+        let pool = self.find_pool(request).ok_or_else(|| {
+            QuoteRejection::Unsupported(match &request.pool_id {
+                Some(pool_id) => format!(
+                    "{} 没有池子 {} 的 {} -> {} 方向",
+                    self.dex_platform, pool_id, request.input_token, request.output_token
+                ),
+                None => format!(
+                    "{} 没有 {} -> {} 交易对",
+                    self.dex_platform, request.input_token, request.output_token
+                ),
+            })
+        })?;
+
+        // 先从输入中扣除费用，剩余部分经过池子曲线
+        let fee_amount = request.amount * pool.fee_rate;
+        let amount_after_fee = request.amount - fee_amount;
+
+        let curve = ConstantProductCurve::from_rate(pool.liquidity, pool.exchange_rate);
+        let output_amount = curve.get_amount_out(amount_after_fee).ok_or_else(|| {
+            QuoteRejection::InsufficientLiquidity(format!(
+                "{} 流动性不足以承接 {} {}",
+                self.dex_platform, request.amount, request.input_token
+            ))
+        })?;
+        let price_impact = curve.price_impact(amount_after_fee).unwrap_or(dec!(1));
+
+        Ok(QuoteResponse {
+            input_amount: request.amount,
+            output_amount,
+            exchange_rate: pool.exchange_rate,
+            price_impact,
+            liquidity_available: pool.liquidity,
+            fee_amount,
+        })
+    }
+}
+
+/// 通过 HTTP 获取报价的提供者
+///
+/// 以 `GET {base_url}/quote?input_token=..&output_token=..&amount=..&dex_platform=..`
+/// 请求报价（请求中指定了 `pool_id`、`input_mint`、`output_mint` 时一并作为查询参数），
+/// 响应体为 JSON 格式的 `QuoteResponse`。
+#[derive(Debug, Clone)]
+pub struct HttpQuoteProvider {
+    dex_platform: String,
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl HttpQuoteProvider {
+    pub fn new(dex_platform: impl Into<String>, base_url: impl Into<String>) -> Self {
+        Self::with_client(dex_platform, base_url, reqwest::Client::new())
+    }
+
+    /// 使用自定义的 HTTP 客户端（如设置了代理或默认请求头）
+    pub fn with_client(
+        dex_platform: impl Into<String>,
+        base_url: impl Into<String>,
+        client: reqwest::Client,
+    ) -> Self {
+        Self {
+            dex_platform: dex_platform.into(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            client,
+        }
+    }
+
+    /// 报价接口的基础 URL
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+}
+
+#[async_trait]
+impl QuoteProvider for HttpQuoteProvider {
+    fn dex_platform(&self) -> &str {
+        &self.dex_platform
+    }
+
+    async fn fetch_quote(&self, request: &QuoteRequest) -> Result<QuoteResponse> {
+        let url = format!("{}/quote", self.base_url);
+        debug!("🌐 请求 {} 报价: {}", self.dex_platform, url);
+
+        let response = self
+            .client
+            .get(&url)
+            .query(request)
+            .send()
+            .await
+            .with_context(|| format!("请求 {} 报价失败", self.dex_platform))?;
+
+        // 4xx 表示请求本身有误，重试也不会成功；408 和 429 是限流或超时，仍可重试
+        let status = response.status();
+        if status.is_client_error()
+            && status != reqwest::StatusCode::REQUEST_TIMEOUT
+            && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+        {
+            return Err(QuoteRejection::Rejected {
+                dex_platform: self.dex_platform.clone(),
+                status: status.as_u16(),
+            }
+            .into());
+        }
+
+        let quote: QuoteResponse = response
+            .error_for_status()
+            .with_context(|| format!("{} 报价接口返回错误状态", self.dex_platform))?
+            .json()
+            .await
+            .with_context(|| format!("无法解析 {} 的报价响应", self.dex_platform))?;
+
+        // 报价与请求不一致说明接口本身出了问题，按普通错误处理，计入熔断失败
+        if quote.input_amount != request.amount {
+            anyhow::bail!(
+                "{} 返回的输入数量 {} 与请求的 {} 不一致",
+                self.dex_platform,
+                quote.input_amount,
+                request.amount
+            );
+        }
+        Ok(quote)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    fn request(amount: Decimal, pool_id: Option<&str>) -> QuoteRequest {
+        QuoteRequest {
+            input_token: "USDC".to_string(),
+            output_token: "SOL".to_string(),
+            amount,
+            dex_platform: "Orca".to_string(),
+            pool_id: pool_id.map(str::to_string),
+            input_mint: Some("usdc-mint".to_string()),
+            output_mint: Some("sol-mint".to_string()),
+        }
+    }
+
+    fn provider() -> MockQuoteProvider {
+        MockQuoteProvider::new("Orca")
+            .with_pool(
+                "orca-30bp",
+                "usdc-mint",
+                "sol-mint",
+                dec!(0.001),
+                dec!(1000000),
+                dec!(0.003),
+            )
+            .with_pool(
+                "orca-5bp",
+                "usdc-mint",
+                "sol-mint",
+                dec!(0.00102),
+                dec!(200000),
+                dec!(0.0005),
+            )
+    }
+
+    #[tokio::test]
+    async fn mock_quotes_are_deterministic() {
+        let provider = provider();
+        let first = provider
+            .fetch_quote(&request(dec!(1000), Some("orca-30bp")))
+            .await
+            .unwrap();
+        let second = provider
+            .fetch_quote(&request(dec!(1000), Some("orca-30bp")))
+            .await
+            .unwrap();
+
+        assert_eq!(first.output_amount, second.output_amount);
+        assert_eq!(first.input_amount, dec!(1000));
+        assert_eq!(first.fee_amount, dec!(3));
+        assert_eq!(first.exchange_rate, dec!(0.001));
+        // 恒定乘积曲线的输出低于按现货汇率计算的输出
+        assert!(first.output_amount < dec!(997) * dec!(0.001));
+        assert!(first.price_impact > dec!(0));
+    }
+
+    #[tokio::test]
+    async fn mock_quotes_the_requested_pool() {
+        let provider = provider();
+        let wide = provider
+            .fetch_quote(&request(dec!(100), Some("orca-30bp")))
+            .await
+            .unwrap();
+        let narrow = provider
+            .fetch_quote(&request(dec!(100), Some("orca-5bp")))
+            .await
+            .unwrap();
+        assert_eq!(wide.exchange_rate, dec!(0.001));
+        assert_eq!(narrow.exchange_rate, dec!(0.00102));
+
+        let unknown = provider
+            .fetch_quote(&request(dec!(100), Some("orca-1bp")))
+            .await;
+        assert!(unknown.is_err());
+    }
+
+    #[tokio::test]
+    async fn mock_rejects_unknown_pair() {
+        let mut reversed = request(dec!(100), None);
+        std::mem::swap(&mut reversed.input_mint, &mut reversed.output_mint);
+        std::mem::swap(&mut reversed.input_token, &mut reversed.output_token);
+        assert!(provider().supports(&request(dec!(100), None)));
+        assert!(!provider().supports(&reversed));
+        let error = provider().fetch_quote(&reversed).await.unwrap_err();
+        assert!(QuoteRejection::is_rejection(&error), "{}", error);
+    }
+
+    /// 在本地端口上启动只响应一次的 HTTP 桩服务器，返回基础 URL 和收到的请求行
+    async fn stub_server(status: &'static str, body: &'static str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            let mut buffer = [0u8; 1024];
+            while !received.windows(4).any(|window| window == b"\r\n\r\n") {
+                let read = socket.read(&mut buffer).await.unwrap();
+                if read == 0 {
+                    break;
+                }
+                received.extend_from_slice(&buffer[..read]);
+            }
+            let response = format!(
+                "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            let received = String::from_utf8_lossy(&received).to_string();
+            received.lines().next().unwrap_or_default().to_string()
+        });
+        (format!("http://{}/", addr), handle)
+    }
+
+    const QUOTE_BODY: &str = r#"{"input_amount":"100","output_amount":"0.0997","exchange_rate":"0.001","price_impact":"0.0001","liquidity_available":"1000000","fee_amount":"0.3"}"#;
+
+    #[tokio::test]
+    async fn http_provider_parses_quote() {
+        let (base_url, server) = stub_server("200 OK", QUOTE_BODY).await;
+        let provider = HttpQuoteProvider::new("Orca", base_url);
+
+        let quote = provider
+            .fetch_quote(&request(dec!(100), Some("orca-30bp")))
+            .await
+            .unwrap();
+        assert_eq!(quote.output_amount, dec!(0.0997));
+        assert_eq!(quote.fee_amount, dec!(0.3));
+
+        let request_line = server.await.unwrap();
+        assert!(request_line.starts_with("GET /quote?"), "{}", request_line);
+        for param in [
+            "input_token=USDC",
+            "amount=100",
+            "pool_id=orca-30bp",
+            "input_mint=usdc-mint",
+        ] {
+            assert!(
+                request_line.contains(param),
+                "{} 缺少 {}",
+                request_line,
+                param
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn http_provider_rejects_error_status() {
+        let (base_url, _server) =
+            stub_server("500 Internal Server Error", r#"{"error":"boom"}"#).await;
+        let provider = HttpQuoteProvider::new("Orca", base_url);
+        let error = provider
+            .fetch_quote(&request(dec!(100), None))
+            .await
+            .unwrap_err();
+        // 服务端错误可能是暂时的，应当重试
+        assert!(!QuoteRejection::is_rejection(&error), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn http_provider_classifies_client_errors() {
+        for (status, rejected) in [
+            ("400 Bad Request", true),
+            ("404 Not Found", true),
+            ("408 Request Timeout", false),
+            ("429 Too Many Requests", false),
+        ] {
+            let (base_url, _server) = stub_server(status, r#"{"error":"nope"}"#).await;
+            let provider = HttpQuoteProvider::new("Orca", base_url);
+            let error = provider
+                .fetch_quote(&request(dec!(100), None))
+                .await
+                .unwrap_err();
+            assert_eq!(
+                QuoteRejection::is_rejection(&error),
+                rejected,
+                "{}: {}",
+                status,
+                error
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn http_provider_errors_on_input_amount_mismatch() {
+        let (base_url, _server) = stub_server("200 OK", QUOTE_BODY).await;
+        let provider = HttpQuoteProvider::new("Orca", base_url);
+        let error = provider
+            .fetch_quote(&request(dec!(250), None))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("不一致"), "{}", error);
+        assert!(!QuoteRejection::is_rejection(&error));
+    }
+}
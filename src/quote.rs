@@ -1,20 +1,40 @@
+use crate::breaker::{CircuitBreaker, CircuitState};
+use crate::cache::QuoteCache;
+use crate::metrics::QuoteMetrics;
+use crate::provider::{QuoteProvider, QuoteRejection};
 use crate::types::*;
 use anyhow::Result;
+use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
+use futures::future::{BoxFuture, FutureExt, Shared};
+use rand::Rng;
 use log::{debug, info, warn};
 use rust_decimal::Decimal;
-use rust_decimal_macros::dec;
+use serde::Serialize;
+use std::collections::BTreeMap;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
+use tokio::task::JoinHandle;
 
 /// 处理来自 DEX 平台的实时报价服务
 pub struct QuoteService {
-    /// 报价缓存，键为缓存键，值为带过期时间的缓存报价
-    cache: Arc<DashMap<String, CachedQuote>>,
+    /// 容量有限的 LRU 报价缓存，在克隆之间共享
+    cache: QuoteCache,
+    /// 缓存键 -> 进行中的报价请求，用于合并并发的相同请求
+    in_flight: Arc<DashMap<String, QuoteFlight>>,
+    /// DEX 平台名称 -> 报价提供者
+    providers: Arc<DashMap<String, Arc<dyn QuoteProvider>>>,
+    /// 按 DEX 平台的熔断器，在克隆之间共享
+    breaker: Arc<CircuitBreaker>,
+    /// 缓存命中和报价失败计数，在克隆之间共享
+    metrics: Arc<QuoteMetrics>,
     /// 报价服务配置参数
     config: QuoteConfig,
 }
 
+/// 进行中的报价请求，可被多个调用者共同等待
+type QuoteFlight = Shared<BoxFuture<'static, Result<QuoteResponse, Arc<anyhow::Error>>>>;
+
 /// 报价服务配置
 #[derive(Debug, Clone)]
 pub struct QuoteConfig {
@@ -22,10 +42,26 @@ pub struct QuoteConfig {
     pub cache_ttl_seconds: u64,
     /// 获取报价失败时的最大重试次数
     pub max_retries: u32,
-    /// 请求超时时间（秒）
+    /// 请求超时时间（秒），每次尝试单独计时
     pub timeout_seconds: u64,
+    /// 多 DEX 报价的总截止时间（秒），超时未返回的 DEX 不计入结果
+    pub multi_dex_timeout_seconds: u64,
     /// 是否启用缓存功能
     pub enable_cache: bool,
+    /// 报价缓存的最大条目数，超出时淘汰最久未使用的条目
+    pub cache_capacity: usize,
+    /// 缓存键中数量保留的有效数字位数，`None` 表示按精确数量缓存
+    ///
+    /// 同一分桶内的请求共享报价，命中时按请求数量等比例换算输出和费用。
+    pub amount_bucket_significant_digits: Option<u32>,
+    /// 重试退避的基础时间（毫秒），第 n 次重试的退避上限为基础时间的 2^n 倍
+    pub retry_base_delay_ms: u64,
+    /// 重试退避的最大时间（毫秒）
+    pub retry_max_delay_ms: u64,
+    /// 触发 DEX 熔断的连续失败次数（重试耗尽后计一次失败）
+    pub circuit_failure_threshold: u32,
+    /// DEX 熔断后的冷却时间（秒）
+    pub circuit_cooldown_seconds: u64,
 }
 
 impl Default for QuoteConfig {
@@ -34,146 +70,293 @@ impl Default for QuoteConfig {
             cache_ttl_seconds: 30,
             max_retries: 3,
             timeout_seconds: 10,
+            multi_dex_timeout_seconds: 5,
             enable_cache: true,
+            cache_capacity: 10_000,
+            amount_bucket_significant_digits: None,
+            retry_base_delay_ms: 100,
+            retry_max_delay_ms: 2_000,
+            circuit_failure_threshold: 5,
+            circuit_cooldown_seconds: 30,
         }
     }
 }
 
-/// 带过期时间的缓存报价
-#[derive(Debug, Clone)]
-struct CachedQuote {
-    /// 缓存的报价响应
-    quote: QuoteResponse,
-    /// 缓存条目的过期时间
-    expires_at: Instant,
-}
-
 impl QuoteService {
+    /// 创建报价服务，报价提供者需通过 `register_provider` 注册
     pub fn new() -> Self {
+        Self::with_config(QuoteConfig::default())
+    }
+
+    /// 用指定配置创建报价服务
+    pub fn with_config(config: QuoteConfig) -> Self {
         Self {
-            cache: Arc::new(DashMap::new()),
-            config: QuoteConfig::default(),
+            cache: QuoteCache::new(config.cache_capacity, config.cache_ttl_seconds),
+            in_flight: Arc::new(DashMap::new()),
+            providers: Arc::new(DashMap::new()),
+            breaker: Arc::new(CircuitBreaker::new(
+                config.circuit_failure_threshold,
+                Duration::from_secs(config.circuit_cooldown_seconds),
+            )),
+            metrics: Arc::new(QuoteMetrics::new()),
+            config,
         }
     }
 
+    /// 报价服务配置
+    pub fn config(&self) -> &QuoteConfig {
+        &self.config
+    }
+
+    /// DEX 平台当前的熔断状态
+    pub fn circuit_state(&self, dex_platform: &str) -> CircuitState {
+        self.breaker.state(dex_platform)
+    }
+
+    /// 注册报价提供者，替换同一 DEX 平台已有的提供者
+    pub fn register_provider(&self, provider: Arc<dyn QuoteProvider>) {
+        let dex_platform = provider.dex_platform().to_string();
+        info!("🔌 注册 {} 报价提供者", dex_platform);
+        self.providers.insert(dex_platform, provider);
+    }
+
+    /// 移除 DEX 平台的报价提供者
+    pub fn remove_provider(&self, dex_platform: &str) -> Option<Arc<dyn QuoteProvider>> {
+        self.providers.remove(dex_platform).map(|(_, provider)| provider)
+    }
+
+    /// 是否有已注册的报价提供者支持该请求
+    pub fn supports(&self, request: &QuoteRequest) -> bool {
+        self.providers
+            .get(&request.dex_platform)
+            .is_some_and(|provider| provider.supports(request))
+    }
+
+    /// 已注册报价提供者的 DEX 平台名称
+    pub fn dex_platforms(&self) -> Vec<String> {
+        let mut platforms: Vec<String> =
+            self.providers.iter().map(|entry| entry.key().clone()).collect();
+        platforms.sort();
+        platforms
+    }
+
     /// 获取特定交易对的报价
     pub async fn get_quote(&self, request: &QuoteRequest) -> Result<QuoteResponse> {
         let cache_key = self.generate_cache_key(request);
         
         // 首先检查缓存
         if self.config.enable_cache {
-            if let Some(cached) = self.cache.get(&cache_key) {
-                if cached.expires_at > Instant::now() {
-                    debug!("📋 报价缓存命中: {} -> {}", 
-                           request.input_token, request.output_token);
-                    return Ok(cached.quote.clone());
-                } else {
-                    // 移除过期的缓存条目
-                    self.cache.remove(&cache_key);
-                }
+            if let Some(quote) = self.cache.get(&cache_key) {
+                debug!("📋 报价缓存命中: {} -> {}", 
+                       request.input_token, request.output_token);
+                self.metrics.cache.hit();
+                return Ok(rescale_quote(quote, request.amount));
             }
+            self.metrics.cache.miss();
         }
 
-        // 获取新鲜报价
+        // 合并的请求可能来自同一数量分桶内的不同数量
+        let quote = self.fetch_coalesced(request, cache_key).await?;
+        Ok(rescale_quote(quote, request.amount))
+    }
+
+    /// 跳过缓存读取获取新鲜报价，结果照常写入缓存（用于刷新市场数据）
+    ///
+    /// 与 `get_quote` 一样，合并到同一数量分桶的报价会按比例换算到请求的输入数量。
+    pub async fn refresh_quote(&self, request: &QuoteRequest) -> Result<QuoteResponse> {
+        let cache_key = self.generate_cache_key(request);
+        let quote = self.fetch_coalesced(request, cache_key).await?;
+        Ok(rescale_quote(quote, request.amount))
+    }
+
+    /// 合并同一缓存键上并发的报价请求
+    ///
+    /// 第一个调用者发起获取，其余调用者等待同一结果而不是各自请求上游 DEX。
+    /// 获取在独立任务中执行，所有调用者都被取消时仍会完成、写入缓存并移除进行中条目。
+    async fn fetch_coalesced(
+        &self,
+        request: &QuoteRequest,
+        cache_key: String,
+    ) -> Result<QuoteResponse> {
+        let flight = match self.in_flight.entry(cache_key.clone()) {
+            Entry::Occupied(entry) => {
+                debug!("⏳ 等待进行中的报价请求: {}", cache_key);
+                self.metrics.record_coalesced();
+                entry.get().clone()
+            }
+            Entry::Vacant(entry) => {
+                let service = self.clone();
+                let request = request.clone();
+                let task = tokio::spawn(async move {
+                    let result = service.fetch_and_cache(&request, &cache_key).await;
+                    // 结果已写入缓存，之后的调用者直接命中缓存
+                    service.in_flight.remove(&cache_key);
+                    result.map_err(Arc::new)
+                });
+                let flight = async move {
+                    task.await.unwrap_or_else(|e| {
+                        Err(Arc::new(anyhow::anyhow!("报价任务异常结束: {}", e)))
+                    })
+                }
+                .boxed()
+                .shared();
+                entry.insert(flight.clone());
+                flight
+            }
+        };
+
+        flight.await.map_err(|e| anyhow::anyhow!("{:#}", e))
+    }
+
+    /// 获取新鲜报价并写入缓存
+    async fn fetch_and_cache(
+        &self,
+        request: &QuoteRequest,
+        cache_key: &str,
+    ) -> Result<QuoteResponse> {
         let quote = self.fetch_quote_from_dex(request).await?;
-        
-        // 缓存结果
+
         if self.config.enable_cache {
-            let cached_quote = CachedQuote {
-                quote: quote.clone(),
-                expires_at: Instant::now() + Duration::from_secs(self.config.cache_ttl_seconds),
-            };
-            self.cache.insert(cache_key, cached_quote);
+            self.cache.insert(cache_key.to_string(), quote.clone());
         }
 
         Ok(quote)
     }
 
-    /// 从 DEX 平台获取报价（模拟演示）
+    /// 从 DEX 平台注册的报价提供者获取报价
+    ///
+    /// 每次尝试受 `timeout_seconds` 限制，失败后按带抖动的指数退避重试至多 `max_retries` 次；
+    /// 重试耗尽计为该 DEX 的一次失败，连续失败达到阈值后熔断，熔断期间直接返回错误。
+    /// 提供者以 `QuoteRejection` 拒绝的请求不重试，也不计入熔断失败。
     async fn fetch_quote_from_dex(&self, request: &QuoteRequest) -> Result<QuoteResponse> {
         info!("🔍 从 {} 获取报价: {} {} -> {}", 
               request.dex_platform, request.amount, 
               request.input_token, request.output_token);
 
-        // 模拟 API 调用延迟
-        tokio::time::sleep(Duration::from_millis(50)).await;
+        // 克隆出提供者后释放 DashMap 引用，避免跨 await 持有分片锁
+        let provider = self
+            .providers
+            .get(&request.dex_platform)
+            .map(|entry| entry.value().clone())
+            .ok_or_else(|| anyhow::anyhow!("未注册 {} 的报价提供者", request.dex_platform))?;
 
-        // 根据平台模拟不同的 DEX 响应
-        let (exchange_rate, liquidity, fee_rate) = match request.dex_platform.as_str() {
-            "Raydium" => (dec!(0.001), dec!(1000000), dec!(0.0025)),
-            "Orca" => (dec!(0.00101), dec!(500000), dec!(0.003)),
-            "Meteora" => (dec!(0.00102), dec!(2000000), dec!(0.0035)),
-            _ => (dec!(0.001), dec!(100000), dec!(0.003)),
-        };
+        if !self.breaker.try_acquire(&request.dex_platform) {
+            return Err(anyhow::anyhow!("{} 已熔断，暂时跳过报价", request.dex_platform));
+        }
 
-        // 计算带价格影响的输出数量
-        let base_output = request.amount * exchange_rate;
-        let price_impact = self.calculate_price_impact(request.amount, liquidity);
-        let output_amount = base_output * (dec!(1) - price_impact);
-        
-        // 计算费用
-        let fee_amount = request.amount * fee_rate;
-
-        let quote = QuoteResponse {
-            input_amount: request.amount,
-            output_amount,
-            exchange_rate,
-            price_impact,
-            liquidity_available: liquidity,
-            fee_amount,
-        };
+        let timeout = Duration::from_secs(self.config.timeout_seconds);
+        let mut attempt = 0;
+        let result = loop {
+            let error = match tokio::time::timeout(timeout, provider.fetch_quote(request)).await {
+                Ok(Ok(quote)) => break Ok(quote),
+                Ok(Err(e)) if QuoteRejection::is_rejection(&e) => break Err(e),
+                Ok(Err(e)) => e,
+                Err(_) => anyhow::anyhow!(
+                    "{} 报价超时（{} 秒）",
+                    request.dex_platform,
+                    self.config.timeout_seconds
+                ),
+            };
 
-        debug!("✅ 收到报价: {} {} -> {} {} (汇率: {}, 影响: {})", 
-               request.amount, request.input_token, 
-               output_amount, request.output_token,
-               exchange_rate, price_impact);
+            if attempt >= self.config.max_retries {
+                break Err(error);
+            }
+            attempt += 1;
+            let delay = self.retry_delay(attempt);
+            warn!("🔁 {} 报价失败: {}，{}ms 后第 {} 次重试",
+                  request.dex_platform, error, delay.as_millis(), attempt);
+            tokio::time::sleep(delay).await;
+        };
 
-        Ok(quote)
+        match &result {
+            Ok(quote) => {
+                self.breaker.record_success(&request.dex_platform);
+                debug!("✅ 收到报价: {} {} -> {} {} (汇率: {}, 影响: {})", 
+                       request.amount, request.input_token, 
+                       quote.output_amount, request.output_token,
+                       quote.exchange_rate, quote.price_impact);
+            }
+            Err(e) if QuoteRejection::is_rejection(e) => {
+                // DEX 正常响应了，只是无法为该请求报价
+                self.metrics.record_error(&request.dex_platform);
+                self.breaker.record_success(&request.dex_platform);
+            }
+            Err(_) => {
+                self.metrics.record_error(&request.dex_platform);
+                self.breaker.record_failure(&request.dex_platform);
+            }
+        }
+        result
     }
 
-    /// 根据交易规模和流动性计算价格影响
-    fn calculate_price_impact(&self, trade_amount: Decimal, liquidity: Decimal) -> Decimal {
-        // 简单的线性价格影响模型
-        // 实际应用中，这将使用实际的 DEX 曲线公式
-        let impact_ratio = trade_amount / liquidity;
-        impact_ratio * dec!(0.5) // 比率的 50% 作为价格影响
+    /// 第 `attempt` 次重试前的退避时间：在 [0, min(最大退避, 基础退避 × 2^attempt)] 内随机选取
+    fn retry_delay(&self, attempt: u32) -> Duration {
+        let cap = self
+            .config
+            .retry_base_delay_ms
+            .saturating_mul(1u64 << attempt.min(20))
+            .min(self.config.retry_max_delay_ms);
+        Duration::from_millis(rand::thread_rng().gen_range(0..=cap))
     }
 
-    /// 为报价请求生成缓存键
+    /// 为报价请求生成缓存键，启用数量分桶时数量按有效数字取整
     fn generate_cache_key(&self, request: &QuoteRequest) -> String {
-        format!("{}:{}:{}:{}", 
+        let amount = match self.config.amount_bucket_significant_digits {
+            Some(digits) => request
+                .amount
+                .round_sf(digits.max(1))
+                .unwrap_or(request.amount)
+                .normalize(),
+            None => request.amount,
+        };
+        // 同一交易对可能有多个池子（如不同费率档位），池子和 mint 都参与建键
+        format!("{}:{}:{}:{}:{}", 
                 request.dex_platform, 
-                request.input_token, 
-                request.output_token, 
-                request.amount)
+                request.pool_id.as_deref().unwrap_or("*"),
+                request.input_mint.as_deref().unwrap_or(&request.input_token), 
+                request.output_mint.as_deref().unwrap_or(&request.output_token), 
+                amount)
     }
 
     /// 并行获取多个 DEX 平台的报价
+    ///
+    /// 每个 DEX 平台的请求由 `request` 复制而来，替换 DEX 平台并由提供者自行选择池子。
+    /// 所有平台共享 `multi_dex_timeout_seconds` 的截止时间，慢的 DEX 不会拖住整体结果；
+    /// 超时的获取仍在后台完成并写入缓存。
     pub async fn get_multi_dex_quotes(
         &self,
-        input_token: &str,
-        output_token: &str,
-        amount: Decimal,
+        request: &QuoteRequest,
         dex_platforms: &[String],
     ) -> Result<Vec<(String, QuoteResponse)>> {
         let mut quote_futures = Vec::new();
+        let deadline_seconds = self.config.multi_dex_timeout_seconds;
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(deadline_seconds);
 
         for dex in dex_platforms {
+            // 熔断中的 DEX 直接跳过，不拖慢其余平台
+            if self.breaker.state(dex) == CircuitState::Open {
+                warn!("⏭️  {} 已熔断，跳过报价", dex);
+                continue;
+            }
+
             let request = QuoteRequest {
-                input_token: input_token.to_string(),
-                output_token: output_token.to_string(),
-                amount,
                 dex_platform: dex.clone(),
+                pool_id: None,
+                ..request.clone()
             };
             
             let quote_service = self.clone();
             let future = async move {
-                match quote_service.get_quote(&request).await {
-                    Ok(quote) => Some((dex.clone(), quote)),
-                    Err(e) => {
+                match tokio::time::timeout_at(deadline, quote_service.get_quote(&request)).await {
+                    Ok(Ok(quote)) => Some((dex.clone(), quote)),
+                    Ok(Err(e)) => {
                         warn!("❌ 从 {} 获取报价失败: {}", dex, e);
                         None
                     }
+                    Err(_) => {
+                        warn!("⏱️  {} 未在 {} 秒内返回报价，已跳过", dex, deadline_seconds);
+                        None
+                    }
                 }
             };
             
@@ -186,7 +369,7 @@ impl QuoteService {
         // 过滤掉失败的请求
         let quotes: Vec<(String, QuoteResponse)> = results
             .into_iter()
-            .filter_map(|result| result)
+            .flatten()
             .collect();
 
         info!("📊 从 {} 个 DEX 平台获取了 {} 个报价", 
@@ -197,36 +380,35 @@ impl QuoteService {
 
     /// 清理过期的缓存条目
     pub fn cleanup_cache(&self) {
-        let now = Instant::now();
-        let mut expired_keys = Vec::new();
-
-        for entry in self.cache.iter() {
-            if entry.expires_at <= now {
-                expired_keys.push(entry.key().clone());
-            }
-        }
-
-        let removed_count = expired_keys.len();
-        for key in expired_keys {
-            self.cache.remove(&key);
-        }
-
+        let removed_count = self.cache.cleanup_expired();
         debug!("🧹 清理了 {} 个过期缓存条目", removed_count);
     }
 
+    /// 启动按 `cache_ttl_seconds` 周期清理过期缓存的后台任务
+    ///
+    /// 必须在 tokio 运行时中调用；报价服务的所有克隆被释放后任务自动结束。
+    pub fn spawn_cache_sweeper(&self) -> JoinHandle<()> {
+        self.cache.spawn_sweeper()
+    }
+
     /// 获取缓存统计
     pub fn get_cache_stats(&self) -> CacheStats {
         let total_entries = self.cache.len();
-        let now = Instant::now();
-        let expired_entries = self.cache.iter()
-            .filter(|entry| entry.expires_at <= now)
-            .count();
+        let expired_entries = self.cache.expired_len().min(total_entries);
 
         CacheStats {
             total_entries,
             expired_entries,
             valid_entries: total_entries - expired_entries,
-            cache_hit_rate: 0.85, // 这将从实际使用中计算
+            cache_capacity: self.cache.capacity(),
+            evictions: self.cache.evictions(),
+            cache_hits: self.metrics.cache.hits(),
+            cache_misses: self.metrics.cache.misses(),
+            cache_hit_rate: self.metrics.cache.hit_rate(),
+            errors_by_dex: self.metrics.errors_by_dex(),
+            coalesced_requests: self.metrics.coalesced(),
+            in_flight_requests: self.in_flight.len(),
+            open_circuits: self.breaker.open_circuits(),
         }
     }
 }
@@ -235,13 +417,17 @@ impl Clone for QuoteService {
     fn clone(&self) -> Self {
         Self {
             cache: self.cache.clone(),
+            in_flight: self.in_flight.clone(),
+            providers: self.providers.clone(),
+            breaker: self.breaker.clone(),
+            metrics: self.metrics.clone(),
             config: self.config.clone(),
         }
     }
 }
 
 /// 缓存统计
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct CacheStats {
     /// 缓存中的总条目数
     pub total_entries: usize,
@@ -249,12 +435,206 @@ pub struct CacheStats {
     pub expired_entries: usize,
     /// 有效的条目数
     pub valid_entries: usize,
+    /// 缓存的最大条目数
+    pub cache_capacity: usize,
+    /// 因容量不足被淘汰的条目数
+    pub evictions: u64,
+    /// 缓存命中次数
+    pub cache_hits: u64,
+    /// 缓存未命中次数
+    pub cache_misses: u64,
     /// 缓存命中率（0-1 之间的小数）
     pub cache_hit_rate: f64,
+    /// DEX 平台 -> 报价失败次数
+    pub errors_by_dex: BTreeMap<String, u64>,
+    /// 合并到进行中请求、等待其结果的调用次数
+    pub coalesced_requests: u64,
+    /// 当前进行中的报价请求数
+    pub in_flight_requests: usize,
+    /// 处于熔断或半开状态的 DEX 平台
+    pub open_circuits: BTreeMap<String, CircuitState>,
+}
+
+/// 把报价按比例换算到请求的输入数量
+///
+/// 用于数量分桶：同一分桶内共享的报价按输入数量等比例调整输出和费用，
+/// 汇率和价格影响沿用原报价。
+fn rescale_quote(mut quote: QuoteResponse, amount: Decimal) -> QuoteResponse {
+    if quote.input_amount == amount || quote.input_amount.is_zero() {
+        return quote;
+    }
+    let ratio = amount / quote.input_amount;
+    quote.output_amount *= ratio;
+    quote.fee_amount *= ratio;
+    quote.input_amount = amount;
+    quote
 }
 
 impl Default for QuoteService {
     fn default() -> Self {
         Self::new()
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use rust_decimal_macros::dec;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 延迟一段时间后返回固定报价的提供者
+    struct SlowProvider {
+        dex_platform: &'static str,
+        delay: Duration,
+    }
+
+    #[async_trait]
+    impl QuoteProvider for SlowProvider {
+        fn dex_platform(&self) -> &str {
+            self.dex_platform
+        }
+
+        async fn fetch_quote(&self, request: &QuoteRequest) -> Result<QuoteResponse> {
+            tokio::time::sleep(self.delay).await;
+            Ok(QuoteResponse {
+                input_amount: request.amount,
+                output_amount: request.amount,
+                exchange_rate: dec!(1),
+                price_impact: dec!(0),
+                liquidity_available: dec!(1000000),
+                fee_amount: dec!(0),
+            })
+        }
+    }
+
+    /// 拒绝所有请求并记录调用次数的提供者
+    #[derive(Default)]
+    struct RejectingProvider {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl QuoteProvider for RejectingProvider {
+        fn dex_platform(&self) -> &str {
+            "Rejecting"
+        }
+
+        async fn fetch_quote(&self, request: &QuoteRequest) -> Result<QuoteResponse> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Err(QuoteRejection::Unsupported(format!(
+                "没有 {} -> {} 交易对",
+                request.input_token, request.output_token
+            ))
+            .into())
+        }
+    }
+
+    fn request() -> QuoteRequest {
+        QuoteRequest {
+            input_token: "USDC".to_string(),
+            output_token: "USDT".to_string(),
+            amount: dec!(100),
+            dex_platform: "Slow".to_string(),
+            pool_id: None,
+            input_mint: None,
+            output_mint: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn cancelled_callers_do_not_leak_in_flight_entries() {
+        let service = QuoteService::new();
+        service.register_provider(Arc::new(SlowProvider {
+            dex_platform: "Slow",
+            delay: Duration::from_millis(50),
+        }));
+
+        let caller = {
+            let service = service.clone();
+            tokio::spawn(async move { service.get_quote(&request()).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(service.get_cache_stats().in_flight_requests, 1);
+        caller.abort();
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(service.get_cache_stats().in_flight_requests, 0);
+        assert_eq!(service.get_cache_stats().total_entries, 1);
+    }
+
+    #[tokio::test]
+    async fn rejected_quotes_skip_retries_and_breaker() {
+        let service = QuoteService::with_config(QuoteConfig {
+            max_retries: 3,
+            circuit_failure_threshold: 2,
+            ..QuoteConfig::default()
+        });
+        let provider = Arc::new(RejectingProvider::default());
+        service.register_provider(provider.clone());
+
+        let request = QuoteRequest {
+            dex_platform: "Rejecting".to_string(),
+            ..request()
+        };
+        for _ in 0..3 {
+            assert!(service.get_quote(&request).await.is_err());
+        }
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 3);
+        assert_eq!(service.circuit_state("Rejecting"), CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn coalesced_refresh_is_rescaled_to_request_amount() {
+        let service = QuoteService::with_config(QuoteConfig {
+            amount_bucket_significant_digits: Some(1),
+            ..QuoteConfig::default()
+        });
+        service.register_provider(Arc::new(SlowProvider {
+            dex_platform: "Slow",
+            delay: Duration::from_millis(50),
+        }));
+
+        let first = {
+            let service = service.clone();
+            tokio::spawn(async move { service.refresh_quote(&request()).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let second = service
+            .refresh_quote(&QuoteRequest {
+                amount: dec!(120),
+                ..request()
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(service.get_cache_stats().coalesced_requests, 1);
+        assert_eq!(second.input_amount, dec!(120));
+        assert_eq!(second.output_amount, dec!(120));
+        assert_eq!(first.await.unwrap().unwrap().output_amount, dec!(100));
+    }
+
+    #[tokio::test]
+    async fn multi_dex_quotes_stop_at_deadline() {
+        let service = QuoteService::with_config(QuoteConfig {
+            multi_dex_timeout_seconds: 1,
+            ..QuoteConfig::default()
+        });
+        service.register_provider(Arc::new(SlowProvider {
+            dex_platform: "Fast",
+            delay: Duration::ZERO,
+        }));
+        service.register_provider(Arc::new(SlowProvider {
+            dex_platform: "Slow",
+            delay: Duration::from_secs(5),
+        }));
+
+        let started = std::time::Instant::now();
+        let quotes = service
+            .get_multi_dex_quotes(&request(), &["Fast".to_string(), "Slow".to_string()])
+            .await
+            .unwrap();
+        assert!(started.elapsed() < Duration::from_secs(3));
+        let dexes: Vec<&str> = quotes.iter().map(|(dex, _)| dex.as_str()).collect();
+        assert_eq!(dexes, ["Fast"]);
+    }
+}
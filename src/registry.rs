@@ -0,0 +1,284 @@
+use crate::types::Token;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use thiserror::Error;
+
+/// 按 mint 地址和符号索引的代币注册表
+///
+/// 同一符号可能对应多个 mint，按符号查找时只有结果唯一（或候选中恰好一个已验证）才会返回。
+#[derive(Debug, Clone, Default)]
+pub struct TokenRegistry {
+    /// mint 地址 -> 代币记录
+    tokens: HashMap<String, TokenRecord>,
+    /// 归一化的符号或别名 -> mint 地址集合
+    symbol_index: HashMap<String, BTreeSet<String>>,
+}
+
+/// 注册表中的代币记录
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenRecord {
+    /// 代币信息
+    #[serde(flatten)]
+    pub token: Token,
+    /// 是否为已验证的代币
+    pub verified: bool,
+    /// 除符号外可用于查找该代币的别名
+    pub aliases: Vec<String>,
+}
+
+/// 代币查找错误
+#[derive(Debug, Clone, Error)]
+pub enum TokenLookupError {
+    /// 没有符号、别名或 mint 地址与查询匹配
+    #[error("未找到代币: {0}")]
+    NotFound(String),
+    /// 符号对应多个代币，且其中已验证的代币不是恰好一个
+    ///
+    /// 候选中恰好一个已验证时 `resolve` 直接返回它，不会产生该错误。
+    #[error("代币符号 {symbol} 对应多个代币，请改用 mint 地址指定: {}", format_candidates(.candidates))]
+    Ambiguous {
+        /// 查询使用的符号
+        symbol: String,
+        /// 所有匹配的候选代币
+        candidates: Vec<Token>,
+    },
+}
+
+fn format_candidates(candidates: &[Token]) -> String {
+    candidates
+        .iter()
+        .map(|token| format!("{} ({})", token.symbol, token.address))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl TokenRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册或更新代币，保留已有的验证标记和别名
+    pub fn upsert(&mut self, token: Token) {
+        let address = token.address.clone();
+        if let Some(previous) = self.tokens.get(&address) {
+            let previous_symbol = previous.token.symbol.clone();
+            let aliases = previous.aliases.clone();
+            self.unindex(&previous_symbol, &address);
+            // 旧符号可能与某个别名归一化后相同，重新索引别名以免被一并移除
+            for alias in &aliases {
+                self.index(alias, &address);
+            }
+        }
+        self.index(&token.symbol, &address);
+
+        self.tokens
+            .entry(address)
+            .and_modify(|record| record.token = token.clone())
+            .or_insert(TokenRecord {
+                token,
+                verified: false,
+                aliases: Vec::new(),
+            });
+    }
+
+    /// 移除代币及其符号和别名索引
+    pub fn remove(&mut self, address: &str) -> Option<TokenRecord> {
+        let record = self.tokens.remove(address)?;
+        self.unindex(&record.token.symbol, address);
+        for alias in &record.aliases {
+            self.unindex(alias, address);
+        }
+        Some(record)
+    }
+
+    /// 设置代币的验证标记，代币不存在时返回 `false`
+    pub fn set_verified(&mut self, address: &str, verified: bool) -> bool {
+        match self.tokens.get_mut(address) {
+            Some(record) => {
+                record.verified = verified;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 为代币添加别名，代币不存在时返回 `false`
+    pub fn add_alias(&mut self, address: &str, alias: &str) -> bool {
+        let Some(record) = self.tokens.get_mut(address) else {
+            return false;
+        };
+        if !record
+            .aliases
+            .iter()
+            .any(|existing| normalize(existing) == normalize(alias))
+        {
+            record.aliases.push(alias.to_string());
+        }
+        self.index(alias, address);
+        true
+    }
+
+    /// 按 mint 地址获取代币记录
+    pub fn get(&self, address: &str) -> Option<&TokenRecord> {
+        self.tokens.get(address)
+    }
+
+    /// 按 mint 地址、符号或别名解析代币
+    ///
+    /// mint 地址优先精确匹配；符号和别名不区分大小写。
+    ///
+    /// 符号对应多个代币时按“已验证优先”规则处理：候选中恰好有一个已验证代币则返回它；
+    /// 没有或有多个已验证代币时返回列出全部候选的 [`TokenLookupError::Ambiguous`]。
+    pub fn resolve(&self, query: &str) -> Result<&Token, TokenLookupError> {
+        if let Some(record) = self.tokens.get(query) {
+            return Ok(&record.token);
+        }
+
+        let candidates: Vec<&TokenRecord> = self
+            .symbol_index
+            .get(&normalize(query))
+            .into_iter()
+            .flatten()
+            .filter_map(|address| self.tokens.get(address))
+            .collect();
+
+        match candidates.as_slice() {
+            [] => Err(TokenLookupError::NotFound(query.to_string())),
+            [record] => Ok(&record.token),
+            _ => {
+                let mut verified = candidates.iter().filter(|record| record.verified);
+                match (verified.next(), verified.next()) {
+                    (Some(record), None) => Ok(&record.token),
+                    _ => Err(TokenLookupError::Ambiguous {
+                        symbol: query.to_string(),
+                        candidates: candidates
+                            .iter()
+                            .map(|record| record.token.clone())
+                            .collect(),
+                    }),
+                }
+            }
+        }
+    }
+
+    /// 注册表中的代币数量
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// 注册表是否为空
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// 遍历所有代币记录
+    pub fn records(&self) -> impl Iterator<Item = &TokenRecord> {
+        self.tokens.values()
+    }
+
+    fn index(&mut self, key: &str, address: &str) {
+        self.symbol_index
+            .entry(normalize(key))
+            .or_default()
+            .insert(address.to_string());
+    }
+
+    fn unindex(&mut self, key: &str, address: &str) {
+        let key = normalize(key);
+        if let Some(addresses) = self.symbol_index.get_mut(&key) {
+            addresses.remove(address);
+            if addresses.is_empty() {
+                self.symbol_index.remove(&key);
+            }
+        }
+    }
+}
+
+/// 符号和别名的归一化形式（不区分大小写）
+fn normalize(symbol: &str) -> String {
+    symbol.trim().to_ascii_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(symbol: &str, address: &str) -> Token {
+        Token {
+            symbol: symbol.to_string(),
+            address: address.to_string(),
+            decimals: 6,
+        }
+    }
+
+    #[test]
+    fn resolves_by_mint_and_case_insensitive_symbol() {
+        let mut registry = TokenRegistry::new();
+        registry.upsert(token("USDC", "usdc-mint"));
+
+        assert_eq!(registry.resolve("usdc-mint").unwrap().symbol, "USDC");
+        assert_eq!(registry.resolve("usdc").unwrap().address, "usdc-mint");
+        assert!(matches!(
+            registry.resolve("USDT"),
+            Err(TokenLookupError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn resolves_by_alias() {
+        let mut registry = TokenRegistry::new();
+        registry.upsert(token("SOL", "sol-mint"));
+        assert!(registry.add_alias("sol-mint", "wSOL"));
+
+        assert_eq!(registry.resolve("WSOL").unwrap().address, "sol-mint");
+        assert!(!registry.add_alias("missing-mint", "X"));
+    }
+
+    #[test]
+    fn ambiguous_symbol_unless_exactly_one_verified() {
+        let mut registry = TokenRegistry::new();
+        registry.upsert(token("USDC", "usdc-mint"));
+        registry.upsert(token("USDC", "fake-usdc-mint"));
+
+        match registry.resolve("USDC") {
+            Err(TokenLookupError::Ambiguous { symbol, candidates }) => {
+                assert_eq!(symbol, "USDC");
+                assert_eq!(candidates.len(), 2);
+            }
+            other => panic!("应返回歧义错误: {:?}", other),
+        }
+
+        registry.set_verified("usdc-mint", true);
+        assert_eq!(registry.resolve("USDC").unwrap().address, "usdc-mint");
+
+        registry.set_verified("fake-usdc-mint", true);
+        assert!(matches!(
+            registry.resolve("USDC"),
+            Err(TokenLookupError::Ambiguous { .. })
+        ));
+    }
+
+    #[test]
+    fn symbol_change_on_upsert_reindexes() {
+        let mut registry = TokenRegistry::new();
+        registry.upsert(token("SOL", "sol-mint"));
+        registry.add_alias("sol-mint", "wsol");
+        registry.set_verified("sol-mint", true);
+
+        registry.upsert(token("WSOL", "sol-mint"));
+
+        assert!(matches!(
+            registry.resolve("SOL"),
+            Err(TokenLookupError::NotFound(_))
+        ));
+        assert_eq!(registry.resolve("WSOL").unwrap().symbol, "WSOL");
+        let record = registry.get("sol-mint").unwrap();
+        assert!(record.verified);
+        assert_eq!(record.aliases, vec!["wsol".to_string()]);
+
+        // 旧符号与别名归一化后相同时，别名仍然可用
+        registry.upsert(token("WRAPPED", "sol-mint"));
+        assert_eq!(registry.resolve("wsol").unwrap().address, "sol-mint");
+        assert_eq!(registry.len(), 1);
+    }
+}
@@ -1,18 +1,36 @@
+use crate::cache::RouteCache;
 use crate::graph::RoutingGraph;
-use crate::quote::QuoteService;
+use crate::metrics::{HistogramSnapshot, NoRouteReason, PairStats, RouterMetrics};
+use crate::provider::MockQuoteProvider;
+use crate::quote::{CacheStats, QuoteService};
+use crate::registry::TokenLookupError;
 use crate::types::*;
+use crate::utils::{CacheUtils, MathUtils};
 use anyhow::Result;
-use log::{info, warn};
+use futures::stream::{self, StreamExt};
+use log::{debug, info, warn};
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
-use std::time::Instant;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
 
 /// 协调路由算法的主要 Metis 路由器
 pub struct MetisRouter {
-    /// 路由图，包含所有代币和交易对信息
-    graph: RoutingGraph,
+    /// 路由图的当前快照，包含所有代币和交易对信息
+    ///
+    /// 读取方克隆快照后在其上搜索；市场数据刷新在写锁内替换快照，读取方要么看到旧图，要么看到完整更新后的新图。
+    graph: RwLock<Arc<RoutingGraph>>,
+    /// 保证同一时间只有一次市场数据刷新
+    refresh_lock: tokio::sync::Mutex<()>,
     /// 报价服务，用于获取实时价格
     quote_service: QuoteService,
+    /// 路由结果缓存
+    route_cache: RouteCache,
+    /// 缓存、延迟和交易对成功率指标
+    metrics: RouterMetrics,
     /// 路由器配置参数
     config: RouterConfig,
 }
@@ -22,10 +40,14 @@ impl MetisRouter {
         let config = RouterConfig::default();
         let graph = RoutingGraph::new(config.clone());
         let quote_service = QuoteService::new();
+        let route_cache = RouteCache::new(config.route_cache_capacity, config.cache_ttl_seconds);
         
         Self {
-            graph,
+            graph: RwLock::new(Arc::new(graph)),
+            refresh_lock: tokio::sync::Mutex::new(()),
             quote_service,
+            route_cache,
+            metrics: RouterMetrics::new(),
             config,
         }
     }
@@ -33,39 +55,143 @@ impl MetisRouter {
     /// 用示例数据初始化路由器（用于演示）
     pub fn initialize(&mut self) {
         info!("🚀 用示例数据初始化 Metis 路由器");
-        self.graph.initialize_sample_data();
+        self.graph_mut().initialize_sample_data();
+    }
+
+    /// 获取路由器配置
+    pub fn config(&self) -> &RouterConfig {
+        &self.config
+    }
+
+    /// 获取路由图的当前快照，之后的市场数据刷新不影响已取得的快照
+    pub fn graph(&self) -> Arc<RoutingGraph> {
+        self.read_graph().clone()
+    }
+
+    /// 获取可变的路由图，用于增量同步代币和池子
+    ///
+    /// 仍有快照被持有时先复制一份路由图。
+    pub fn graph_mut(&mut self) -> &mut RoutingGraph {
+        let graph = self
+            .graph
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        Arc::make_mut(graph)
+    }
+
+    fn read_graph(&self) -> RwLockReadGuard<'_, Arc<RoutingGraph>> {
+        self.graph.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write_graph(&self) -> RwLockWriteGuard<'_, Arc<RoutingGraph>> {
+        self.graph.write().unwrap_or_else(|poisoned| poisoned.into_inner())
     }
 
     /// 寻找最优路由的主要入口点
-    pub async fn find_optimal_route(&self, request: RouteRequest) -> Result<RouteResponse> {
+    pub async fn find_optimal_route(&self, mut request: RouteRequest) -> Result<RouteResponse> {
         let start_time = Instant::now();
-        
+
+        // ExactOut 模式不使用输入数量，清零以免无意义的取值影响校验和缓存键
+        if request.swap_mode == SwapMode::ExactOut {
+            request.input_amount = Decimal::ZERO;
+        }
+
+        // 交易对统计按解析后的 mint 建键，未通过校验的请求只计入未返回路由的原因，
+        // 避免任意字符串撑大统计表
+        let (input_mint, output_mint) = match self.validate_request(&request) {
+            Ok(mints) => mints,
+            Err(e) => {
+                self.metrics.record_no_route(Self::invalid_request_reason(&e));
+                self.metrics.route_latency.observe_duration(start_time.elapsed());
+                return Err(e);
+            }
+        };
+
+        let result = self.route_request(request, start_time).await;
+        match &result {
+            Ok(response) if response.route.is_none() && response.split_route.is_none() => {
+                self.metrics.record_no_route(NoRouteReason::NoPath);
+            }
+            Err(_) => self.metrics.record_no_route(NoRouteReason::SearchFailed),
+            Ok(_) => {}
+        }
+
+        let success = result
+            .as_ref()
+            .is_ok_and(|response| response.route.is_some() || response.split_route.is_some());
+        self.metrics
+            .record_route(&input_mint, &output_mint, success, start_time.elapsed());
+        result
+    }
+
+    /// 处理已通过校验的路由请求（缓存查找、搜索和缓存写入）
+    async fn route_request(&self, request: RouteRequest, start_time: Instant) -> Result<RouteResponse> {
         info!("🎯 处理路由请求: {} -> {} ({} {})", 
               request.input_token, request.output_token, 
               request.input_amount, request.input_token);
 
-        // 验证请求
-        self.validate_request(&request)?;
+        // 整个请求在同一个图快照上完成，不受并发的市场数据刷新影响
+        let graph = self.graph();
+
+        // 检查路由缓存
+        let cache_key = CacheUtils::generate_route_cache_key(&request)
+            .filter(|_| self.config.enable_caching);
+        if let Some(cache_key) = &cache_key {
+            if let Some(mut cached) = self.route_cache.get(cache_key, &graph) {
+                debug!("📋 路由缓存命中: {} -> {}", request.input_token, request.output_token);
+                self.metrics.route_cache.hit();
+                cached.execution_time_ms = start_time.elapsed().as_millis() as u64;
+                return Ok(cached);
+            }
+            self.metrics.route_cache.miss();
+        }
+        let graph_version = graph.version();
 
         let mut response = RouteResponse {
             request: request.clone(),
             route: None,
             split_route: None,
+            alternatives: Vec::new(),
+            max_input_amount: None,
             execution_time_ms: 0,
             iterations_used: 0,
         };
 
+        // 固定输出模式：反向搜索所需输入最少的单一路由
+        if request.swap_mode == SwapMode::ExactOut {
+            let search = graph.find_exact_out_route(&request).await?;
+            response.iterations_used = search.iterations;
+            self.metrics.iterations.observe(search.iterations as f64);
+            if let Some(route) = search.route {
+                let (_, max_input) = MathUtils::calculate_slippage_bounds(
+                    route.total_input_amount,
+                    request.slippage_tolerance,
+                );
+                response.max_input_amount = Some(max_input);
+                response.route = Some(route);
+                info!("✅ 找到固定输出路由");
+            } else {
+                warn!("❌ 未找到固定输出路由");
+            }
+            response.execution_time_ms = start_time.elapsed().as_millis() as u64;
+            self.cache_response(cache_key, &response, graph_version);
+            return Ok(response);
+        }
+
         // 首先尝试找到单个最优路由
-        if let Some(route) = self.graph.find_optimal_route(&request).await? {
+        let search = graph.find_optimal_route(&request).await?;
+        response.iterations_used = search.iterations;
+        self.metrics.iterations.observe(search.iterations as f64);
+        if let Some(route) = search.route {
             response.route = Some(route);
             info!("✅ 找到单个最优路由");
         } else {
             warn!("⚠️  未找到单个路由，尝试分割路由");
         }
 
-        // 如果启用了分割路由且没有找到单个路由，尝试分割路由
-        if request.enable_split_routes && response.route.is_none() {
-            if let Some(split_route) = self.graph.find_split_routes(&request).await? {
+        // 如果启用了分割路由，尝试分割路由（大额交易分割后通常优于单一路由）
+        if request.enable_split_routes {
+            if let Some(split_route) = graph.find_split_routes(&request).await? {
                 response.split_route = Some(split_route);
                 info!("✅ 找到分割路由配置");
             }
@@ -73,7 +199,7 @@ impl MetisRouter {
 
         // 如果我们同时有单个和分割路由，比较它们
         if let (Some(single_route), Some(split_route)) = (&response.route, &response.split_route) {
-            let single_better = self.compare_routes(single_route, &split_route);
+            let single_better = self.compare_routes(single_route, split_route);
             if !single_better {
                 info!("🔄 分割路由更好，移除单个路由");
                 response.route = None;
@@ -83,6 +209,12 @@ impl MetisRouter {
             }
         }
 
+        // 如果请求了备选路由，寻找前 K 条不同的单一路径路由
+        if let Some(k) = request.max_alternatives.filter(|k| *k > 0) {
+            response.alternatives = graph.find_k_best_routes(&request, k).await?;
+            info!("🏅 找到 {} 条备选路由", response.alternatives.len());
+        }
+
         response.execution_time_ms = start_time.elapsed().as_millis() as u64;
         
         if response.route.is_some() || response.split_route.is_some() {
@@ -91,13 +223,41 @@ impl MetisRouter {
             warn!("❌ 未找到有效路由");
         }
 
+        self.cache_response(cache_key, &response, graph_version);
         Ok(response)
     }
 
-    /// 验证路由请求
-    fn validate_request(&self, request: &RouteRequest) -> Result<()> {
-        if request.input_amount <= dec!(0) {
-            return Err(anyhow::anyhow!("输入数量必须为正数"));
+    /// 缓存找到了路由的响应（未找到路由的响应不缓存，以便新加入的池子立即生效）
+    ///
+    /// 没有缓存键（缓存已禁用或请求无法序列化）时不缓存。
+    fn cache_response(&self, cache_key: Option<String>, response: &RouteResponse, graph_version: u64) {
+        let Some(cache_key) = cache_key else {
+            return;
+        };
+        if response.route.is_some() || response.split_route.is_some() {
+            self.route_cache
+                .insert(cache_key, response.clone(), graph_version);
+        }
+    }
+
+    /// 路由结果缓存
+    pub fn route_cache(&self) -> &RouteCache {
+        &self.route_cache
+    }
+
+    /// 验证路由请求，返回解析后的输入和输出代币 mint 地址
+    fn validate_request(&self, request: &RouteRequest) -> Result<(String, String)> {
+        match request.swap_mode {
+            SwapMode::ExactIn => {
+                if request.input_amount <= dec!(0) {
+                    return Err(anyhow::anyhow!("输入数量必须为正数"));
+                }
+            }
+            SwapMode::ExactOut => {
+                if request.output_amount.is_none_or(|amount| amount <= dec!(0)) {
+                    return Err(anyhow::anyhow!("ExactOut 模式的输出数量必须为正数"));
+                }
+            }
         }
 
         if request.slippage_tolerance <= dec!(0) || request.slippage_tolerance >= dec!(1) {
@@ -108,41 +268,245 @@ impl MetisRouter {
             return Err(anyhow::anyhow!("最大迭代次数必须大于 0"));
         }
 
-        if request.input_token == request.output_token {
+        if request.max_hops == Some(0) {
+            return Err(anyhow::anyhow!("最大跳数必须大于 0"));
+        }
+
+        if request.filters.allowed_dexes.as_ref().is_some_and(|dexes| dexes.is_empty()) {
+            return Err(anyhow::anyhow!("允许的 DEX 平台列表不能为空"));
+        }
+
+        // 解析代币以便尽早报告未知或不唯一的符号
+        let graph = self.read_graph();
+        let input_token = graph.resolve_token(&request.input_token)?;
+        let output_token = graph.resolve_token(&request.output_token)?;
+        if input_token.address == output_token.address {
             return Err(anyhow::anyhow!("输入和输出代币必须不同"));
         }
 
-        Ok(())
+        // 中间代币过滤条件按 mint 匹配，不唯一的符号无法确定要放行或排除哪个 mint
+        let filters = &request.filters;
+        for entry in filters
+            .allowed_intermediate_tokens
+            .iter()
+            .flatten()
+            .chain(&filters.excluded_intermediate_tokens)
+        {
+            if let Err(error @ TokenLookupError::Ambiguous { .. }) = graph.resolve_token(entry) {
+                return Err(error.into());
+            }
+        }
+
+        Ok((input_token.address.clone(), output_token.address.clone()))
+    }
+
+    /// 将校验错误归类为未返回路由的原因
+    fn invalid_request_reason(error: &anyhow::Error) -> NoRouteReason {
+        match error.downcast_ref::<TokenLookupError>() {
+            Some(TokenLookupError::NotFound(_)) => NoRouteReason::UnknownToken,
+            Some(TokenLookupError::Ambiguous { .. }) => NoRouteReason::AmbiguousToken,
+            None => NoRouteReason::InvalidRequest,
+        }
     }
 
     /// 比较单个路由与分割路由以确定哪个更好
     fn compare_routes(&self, single_route: &Route, split_route: &SplitRoute) -> bool {
-        // 考虑 gas 成本计算有效汇率
-        let single_effective = single_route.effective_rate - single_route.gas_estimate;
-        let split_effective = split_route.effective_rate - split_route.gas_estimate;
-        
-        // 如果有效汇率更高，单个路由更好
-        single_effective > split_effective
+        // 输入不同的路由无法按输出比较，保留承接了完整输入的单一路由
+        if single_route.total_input_amount != split_route.total_input_amount {
+            warn!(
+                "⚠️  分割路由输入 {} 与单一路由输入 {} 不同，不参与比较",
+                split_route.total_input_amount, single_route.total_input_amount
+            );
+            return true;
+        }
+        // 两者输入相同，比较扣除 gas（已折算为输出代币）后的净输出
+        single_route.net_output_amount > split_route.net_output_amount
+    }
+
+    /// 检测路由图中的跨 DEX 套利环路
+    pub fn find_arbitrage_cycles(&self) -> Result<Vec<ArbitrageCycle>> {
+        self.graph().find_arbitrage_cycles()
     }
 
-    /// 获取特定交易对的实时报价
+    /// 获取特定交易对的实时报价，未指定 mint 时按代币注册表解析
     pub async fn get_quote(&self, request: &QuoteRequest) -> Result<QuoteResponse> {
-        self.quote_service.get_quote(request).await
+        let request = self.resolve_quote_mints(request);
+        self.quote_service.get_quote(&request).await
+    }
+
+    /// 并行获取多个 DEX 平台的报价，未指定 mint 时按代币注册表解析
+    pub async fn get_multi_dex_quotes(
+        &self,
+        request: &QuoteRequest,
+        dex_platforms: &[String],
+    ) -> Result<Vec<(String, QuoteResponse)>> {
+        let request = self.resolve_quote_mints(request);
+        self.quote_service
+            .get_multi_dex_quotes(&request, dex_platforms)
+            .await
+    }
+
+    /// 为报价请求补全输入和输出代币的 mint，无法唯一解析的代币保持原样交给提供者
+    fn resolve_quote_mints(&self, request: &QuoteRequest) -> QuoteRequest {
+        let graph = self.read_graph();
+        let resolve = |mint: &Option<String>, token: &str| {
+            mint.clone().or_else(|| {
+                graph
+                    .resolve_token(token)
+                    .ok()
+                    .map(|token| token.address.clone())
+            })
+        };
+        QuoteRequest {
+            input_mint: resolve(&request.input_mint, &request.input_token),
+            output_mint: resolve(&request.output_mint, &request.output_token),
+            ..request.clone()
+        }
+    }
+
+    /// 按路由图当前的边为每个 DEX 平台注册模拟报价提供者（用于演示），返回注册的平台数
+    ///
+    /// 已注册的同名提供者会被替换。
+    pub fn register_mock_providers(&self) -> usize {
+        let graph = self.graph();
+        let mut dex_platforms: Vec<&str> = graph
+            .edges
+            .values()
+            .flatten()
+            .map(|edge| edge.dex_platform.name.as_str())
+            .collect();
+        dex_platforms.sort();
+        dex_platforms.dedup();
+
+        for dex in &dex_platforms {
+            let provider = MockQuoteProvider::from_edges(*dex, graph.edges.values().flatten());
+            self.quote_service.register_provider(Arc::new(provider));
+        }
+        dex_platforms.len()
+    }
+
+    /// 获取报价服务的缓存和错误统计
+    pub fn get_quote_stats(&self) -> CacheStats {
+        self.quote_service.get_cache_stats()
+    }
+
+    /// 获取报价服务
+    pub fn quote_service(&self) -> &QuoteService {
+        &self.quote_service
     }
 
     /// 用新鲜市场数据更新路由图
-    pub async fn update_market_data(&mut self) -> Result<()> {
+    ///
+    /// 通过报价服务为每条边获取新鲜报价（经过重试和熔断），再在写锁内一次性应用到路由图：
+    /// 刷新成功的边更新曲线、汇率、流动性和权重，失败的边保留原有数据并标记为过期。
+    pub async fn update_market_data(&self) -> Result<MarketDataReport> {
+        let _refresh = self.refresh_lock.lock().await;
+        let start_time = Instant::now();
         info!("📊 更新路由图的市场数据");
-        
-        // 在实际实现中，这将：
-        // 1. 从多个 DEX API 获取当前价格
-        // 2. 更新流动性信息
-        // 3. 刷新汇率
-        // 4. 更新边权重
-        
-        // 为了演示，我们只记录更新
-        info!("✅ 市场数据更新成功");
-        Ok(())
+
+        let snapshot = self.graph();
+        let edges_total = snapshot.edges.values().map(Vec::len).sum::<usize>();
+        // 没有提供者能报价的边保持原状，不计为失败也不标记过期
+        let requests: Vec<(EdgeMarketData, QuoteRequest)> = snapshot
+            .edges
+            .values()
+            .flatten()
+            .map(|edge| (edge, Self::market_data_request(edge)))
+            .filter(|(_, request)| self.quote_service.supports(request))
+            .map(|(edge, request)| {
+                let update = EdgeMarketData {
+                    pool_id: edge.pool_id.clone(),
+                    from_token: edge.from_token.address.clone(),
+                    quote: None,
+                };
+                (update, request)
+            })
+            .collect();
+        drop(snapshot);
+        let edges_skipped = edges_total - requests.len();
+
+        let updates: Vec<EdgeMarketData> = stream::iter(requests)
+            .map(|(mut update, request)| async move {
+                match self.quote_service.refresh_quote(&request).await {
+                    Ok(quote) => update.quote = Some(quote),
+                    Err(e) => debug!("⚠️  刷新 {} 的市场数据失败: {}", update.pool_id, e),
+                }
+                update
+            })
+            .buffer_unordered(self.config.market_data_refresh_concurrency.max(1))
+            .collect()
+            .await;
+
+        let edges_refreshed = updates.iter().filter(|update| update.quote.is_some()).count();
+        let (edges_changed, graph_version, stale_edges) = {
+            let mut graph = self.write_graph();
+            let graph = Arc::make_mut(&mut graph);
+            let changed = graph.apply_market_data(&updates);
+            (changed, graph.version(), graph.stale_edge_count())
+        };
+
+        let report = MarketDataReport {
+            edges_total,
+            edges_skipped,
+            edges_refreshed,
+            edges_failed: updates.len() - edges_refreshed,
+            edges_changed,
+            stale_edges,
+            graph_version,
+            execution_time_ms: start_time.elapsed().as_millis() as u64,
+        };
+        if report.edges_failed > 0 {
+            warn!("⚠️  {} 条边的市场数据刷新失败，已标记为过期", report.edges_failed);
+        }
+        info!(
+            "✅ 市场数据更新完成: 刷新 {}/{} 条边（{} 条没有报价来源，已跳过），{} 条发生变化，耗时 {}ms",
+            report.edges_refreshed, report.edges_total, report.edges_skipped,
+            report.edges_changed, report.execution_time_ms
+        );
+        Ok(report)
+    }
+
+    /// 刷新一条边所用的报价请求，以边的最小交易规模（未设置时为 1 个代币）询价
+    ///
+    /// 请求指定池子和 mint，同一交易对在同一 DEX 上的多个池子分别刷新。
+    fn market_data_request(edge: &Edge) -> QuoteRequest {
+        QuoteRequest {
+            input_token: edge.from_token.symbol.clone(),
+            output_token: edge.to_token.symbol.clone(),
+            amount: if edge.min_trade_size > dec!(0) {
+                edge.min_trade_size
+            } else {
+                dec!(1)
+            },
+            dex_platform: edge.dex_platform.name.clone(),
+            pool_id: Some(edge.pool_id.clone()),
+            input_mint: Some(edge.from_token.address.clone()),
+            output_mint: Some(edge.to_token.address.clone()),
+        }
+    }
+
+    /// 启动按固定间隔刷新市场数据的后台任务，路由器被释放后任务自动结束
+    ///
+    /// 必须在 tokio 运行时中调用。首次刷新在一个间隔之后进行。
+    pub fn spawn_market_data_refresh(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let router = Arc::downgrade(self);
+        let interval = interval.max(Duration::from_secs(1));
+        info!("🔄 每 {} 秒刷新一次市场数据", interval.as_secs());
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let Some(router) = router.upgrade() else {
+                    debug!("🛑 路由器已释放，停止刷新市场数据");
+                    break;
+                };
+                if let Err(e) = router.update_market_data().await {
+                    warn!("❌ 市场数据更新失败: {}", e);
+                }
+            }
+        })
     }
 
     /// 分析路由性能并提供见解
@@ -161,7 +525,7 @@ impl MetisRouter {
             
             // 计算总费用
             analysis.total_fees = route.segments.iter()
-                .map(|s| s.input_amount * s.dex_platform.fee_rate)
+                .map(|s| s.fee_amount)
                 .sum();
             
             // 计算效率分数（越高越好）
@@ -189,18 +553,42 @@ impl MetisRouter {
 
     /// 获取路由统计和性能指标
     pub fn get_routing_stats(&self) -> RoutingStats {
+        let latency = self.metrics.route_latency.snapshot();
+        let pair_stats = self.metrics.pair_stats();
+        // 延迟对每个请求（包括未通过校验的请求）都有记录
+        let total_requests = latency.count;
+        let successful_requests: u64 = pair_stats.values().map(|pair| pair.successes).sum();
+
+        let graph = self.graph();
         RoutingStats {
-            total_nodes: self.graph.nodes.len(),
-            total_edges: self.graph.edges.values().map(|v| v.len()).sum(),
-            cache_hit_rate: 0.85, // 示例值
-            avg_execution_time_ms: 45, // 示例值
-            success_rate: 0.92, // 示例值
+            total_nodes: graph.nodes.len(),
+            total_edges: graph.edges.values().map(|v| v.len()).sum(),
+            stale_edges: graph.stale_edge_count(),
+            graph_version: graph.version(),
+            cache_hits: self.metrics.route_cache.hits(),
+            cache_misses: self.metrics.route_cache.misses(),
+            cache_hit_rate: self.metrics.route_cache.hit_rate(),
+            cache_entries: self.route_cache.len(),
+            cache_evictions: self.route_cache.evictions(),
+            avg_execution_time_ms: latency.mean().round() as u64,
+            total_requests,
+            successful_requests,
+            success_rate: if total_requests == 0 {
+                0.0
+            } else {
+                successful_requests as f64 / total_requests as f64
+            },
+            latency,
+            iterations: self.metrics.iterations.snapshot(),
+            pair_stats,
+            no_route_by_reason: self.metrics.no_route_by_reason(),
+            quote_errors_by_dex: self.quote_service.get_cache_stats().errors_by_dex,
         }
     }
 }
 
 /// 路由性能分析
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct RouteAnalysis {
     /// 路由的总跳数（路径段数量）
     pub total_hops: usize,
@@ -214,19 +602,66 @@ pub struct RouteAnalysis {
     pub recommendations: Vec<String>,
 }
 
+/// 一次市场数据刷新的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct MarketDataReport {
+    /// 路由图中的总边数
+    pub edges_total: usize,
+    /// 没有提供者支持、未刷新的边数
+    pub edges_skipped: usize,
+    /// 成功获取报价的边数
+    pub edges_refreshed: usize,
+    /// 刷新失败、被标记为过期的边数
+    pub edges_failed: usize,
+    /// 数据或过期状态发生变化的边数
+    pub edges_changed: usize,
+    /// 刷新后路由图中过期的边数
+    pub stale_edges: usize,
+    /// 刷新后的图版本号
+    pub graph_version: u64,
+    /// 刷新耗时（毫秒）
+    pub execution_time_ms: u64,
+}
+
 /// 路由统计和性能指标
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct RoutingStats {
     /// 路由图中的总节点数（代币数量）
     pub total_nodes: usize,
     /// 路由图中的总边数（交易对数量）
     pub total_edges: usize,
+    /// 最近一次市场数据刷新失败的边数
+    pub stale_edges: usize,
+    /// 路由图的当前版本号
+    pub graph_version: u64,
+    /// 路由缓存命中次数
+    pub cache_hits: u64,
+    /// 路由缓存未命中次数
+    pub cache_misses: u64,
     /// 缓存命中率（0-1 之间的小数）
     pub cache_hit_rate: f64,
+    /// 路由缓存中的条目数
+    pub cache_entries: usize,
+    /// 因容量不足被淘汰的路由缓存条目数
+    pub cache_evictions: u64,
     /// 平均执行时间（毫秒）
     pub avg_execution_time_ms: u64,
+    /// 路由请求总数
+    pub total_requests: u64,
+    /// 找到路由的请求数
+    pub successful_requests: u64,
     /// 路由查找成功率（0-1 之间的小数）
     pub success_rate: f64,
+    /// 路由请求延迟直方图（毫秒）
+    pub latency: HistogramSnapshot,
+    /// 每次搜索使用的迭代次数直方图
+    pub iterations: HistogramSnapshot,
+    /// 通过校验的交易对（`输入 mint->输出 mint`）-> 请求统计
+    pub pair_stats: BTreeMap<String, PairStats>,
+    /// 未返回路由的原因 -> 次数
+    pub no_route_by_reason: BTreeMap<String, u64>,
+    /// DEX 平台 -> 报价失败次数
+    pub quote_errors_by_dex: BTreeMap<String, u64>,
 }
 
 impl Default for MetisRouter {
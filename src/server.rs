@@ -0,0 +1,158 @@
+use crate::exporter::{render_metrics, PROMETHEUS_CONTENT_TYPE};
+use crate::quote::CacheStats;
+use crate::registry::{TokenLookupError, TokenRecord};
+use crate::routing::{MetisRouter, RoutingStats};
+use crate::types::*;
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use log::{info, warn};
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+/// 所有处理函数共享的路由器
+pub type SharedRouter = Arc<MetisRouter>;
+
+/// 构建 HTTP JSON API 的路由表
+///
+/// * `POST /route` - 请求体为 `RouteRequest`，返回 `RouteResponse`
+/// * `POST /quote` - 请求体为 `QuoteRequest`，返回 `QuoteResponse`
+/// * `GET /tokens` - 已注册的代币列表
+/// * `GET /stats` - 路由和报价统计
+/// * `GET /health` - 健康检查
+/// * `GET /metrics` - Prometheus 文本格式的指标
+pub fn app(router: SharedRouter) -> Router {
+    Router::new()
+        .route("/route", post(route))
+        .route("/quote", post(quote))
+        .route("/tokens", get(tokens))
+        .route("/stats", get(stats))
+        .route("/health", get(health))
+        .route("/metrics", get(metrics))
+        .with_state(router)
+}
+
+/// 在指定地址上启动 API 服务器，直到服务器出错为止
+pub async fn serve(router: SharedRouter, addr: SocketAddr) -> anyhow::Result<()> {
+    info!("🌐 Metis API 服务器监听于 http://{}", addr);
+    axum::Server::try_bind(&addr)?
+        .serve(app(router).into_make_service())
+        .await?;
+    Ok(())
+}
+
+/// `/stats` 返回的统计信息
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsResponse {
+    /// 路由统计
+    pub routing: RoutingStats,
+    /// 报价缓存和错误统计
+    pub quotes: CacheStats,
+}
+
+/// `/health` 返回的健康状态
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthResponse {
+    /// 服务状态，路由图中有边时为 "ok"，否则为 "empty"
+    pub status: &'static str,
+    /// 路由图的当前版本号
+    pub graph_version: u64,
+    /// 代币数量
+    pub tokens: usize,
+    /// 边数量
+    pub edges: usize,
+    /// 最近一次市场数据刷新失败的边数
+    pub stale_edges: usize,
+}
+
+/// 以 JSON `{"error": ...}` 返回的 API 错误
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, error: anyhow::Error) -> Self {
+        Self {
+            status,
+            message: error.to_string(),
+        }
+    }
+
+    /// 路由请求的错误：未知代币返回 404，其余校验或搜索错误返回 400
+    fn from_route_error(error: anyhow::Error) -> Self {
+        let status = match error.downcast_ref::<TokenLookupError>() {
+            Some(TokenLookupError::NotFound(_)) => StatusCode::NOT_FOUND,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        Self::new(status, error)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = Json(serde_json::json!({ "error": self.message }));
+        (self.status, body).into_response()
+    }
+}
+
+async fn route(
+    State(router): State<SharedRouter>,
+    Json(request): Json<RouteRequest>,
+) -> Result<Json<RouteResponse>, ApiError> {
+    router
+        .find_optimal_route(request)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            warn!("❌ /route 请求失败: {}", e);
+            ApiError::from_route_error(e)
+        })
+}
+
+async fn quote(
+    State(router): State<SharedRouter>,
+    Json(request): Json<QuoteRequest>,
+) -> Result<Json<QuoteResponse>, ApiError> {
+    // 报价失败来自上游 DEX，按网关错误返回
+    router.get_quote(&request).await.map(Json).map_err(|e| {
+        warn!("❌ /quote 请求失败: {}", e);
+        ApiError::new(StatusCode::BAD_GATEWAY, e)
+    })
+}
+
+async fn tokens(State(router): State<SharedRouter>) -> Json<Vec<TokenRecord>> {
+    let mut tokens: Vec<TokenRecord> = router.graph().token_registry().records().cloned().collect();
+    tokens.sort_by(|a, b| a.token.symbol.cmp(&b.token.symbol));
+    Json(tokens)
+}
+
+async fn stats(State(router): State<SharedRouter>) -> Json<StatsResponse> {
+    Json(StatsResponse {
+        routing: router.get_routing_stats(),
+        quotes: router.get_quote_stats(),
+    })
+}
+
+async fn health(State(router): State<SharedRouter>) -> Json<HealthResponse> {
+    let graph = router.graph();
+    let edges: usize = graph.edges.values().map(|edges| edges.len()).sum();
+    Json(HealthResponse {
+        status: if edges > 0 { "ok" } else { "empty" },
+        graph_version: graph.version(),
+        tokens: graph.nodes.len(),
+        edges,
+        stale_edges: graph.stale_edge_count(),
+    })
+}
+
+async fn metrics(State(router): State<SharedRouter>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)],
+        render_metrics(&router),
+    )
+}
@@ -1,3 +1,5 @@
+use crate::curve::{Curve, PoolCurve};
+use crate::utils::MathUtils;
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 use serde::{Deserialize, Serialize};
@@ -28,6 +30,8 @@ pub struct DexPlatform {
 /// 表示具有流动性约束的交易对边
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Edge {
+    /// 池子标识（同一交易对在不同池子/费率档位上各有一条边）
+    pub pool_id: String,
     /// 源代币（输入代币）
     pub from_token: Token,
     /// 目标代币（输出代币）
@@ -42,13 +46,71 @@ pub struct Edge {
     pub max_trade_size: Decimal,
     /// 该交易对的最小交易规模
     pub min_trade_size: Decimal,
-    /// Bellman-Ford 算法的权重，值为 -log(exchange_rate)
+    /// Bellman-Ford 算法的权重，值为 -log(exchange_rate × (1 - fee_rate))
     pub weight: f64,
+    /// 该交易对所在池子的定价曲线
+    pub curve: Curve,
+    /// 最近一次市场数据刷新是否失败；失败的边保留上一次的数据
+    #[serde(default)]
+    pub stale: bool,
+}
+
+impl Edge {
+    /// 由定价曲线构造边，汇率、流动性和权重均从曲线推导，权重计入 DEX 费率
+    pub fn new(
+        pool_id: String,
+        from_token: Token,
+        to_token: Token,
+        dex_platform: DexPlatform,
+        curve: Curve,
+        max_trade_size: Decimal,
+        min_trade_size: Decimal,
+    ) -> Self {
+        let mut edge = Self {
+            pool_id,
+            from_token,
+            to_token,
+            dex_platform,
+            exchange_rate: dec!(0),
+            liquidity: dec!(0),
+            max_trade_size,
+            min_trade_size,
+            weight: 0.0,
+            curve,
+            stale: false,
+        };
+        edge.recompute();
+        edge
+    }
+
+    /// 由当前曲线和费率重新计算汇率、流动性和权重
+    pub fn recompute(&mut self) {
+        self.exchange_rate = self.curve.spot_price();
+        self.liquidity = self.curve.liquidity();
+        let effective_rate = MathUtils::calculate_effective_rate(
+            self.exchange_rate,
+            self.dex_platform.fee_rate,
+            dec!(0),
+        );
+        self.weight = MathUtils::calculate_edge_weight(effective_rate);
+    }
+
+    /// 扣除交易费用后实际进入池子曲线的输入数量
+    pub fn amount_after_fee(&self, amount_in: Decimal) -> Decimal {
+        amount_in * (dec!(1) - self.dex_platform.fee_rate)
+    }
+
+    /// 该边对给定输入收取的交易费用（以输入代币计）
+    pub fn fee_amount(&self, amount_in: Decimal) -> Decimal {
+        amount_in * self.dex_platform.fee_rate
+    }
 }
 
 /// 路由中的路径段
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PathSegment {
+    /// 执行该段交易的池子标识
+    pub pool_id: String,
     /// 该段的输入代币
     pub from_token: Token,
     /// 该段的输出代币
@@ -57,8 +119,10 @@ pub struct PathSegment {
     pub dex_platform: DexPlatform,
     /// 该段的输入数量
     pub input_amount: Decimal,
-    /// 该段的输出数量
+    /// 该段的输出数量（已扣除交易费用）
     pub output_amount: Decimal,
+    /// 该段收取的交易费用（以该段输入代币计）
+    pub fee_amount: Decimal,
     /// 该段的有效汇率
     pub exchange_rate: Decimal,
     /// 该段的价格影响（滑点）
@@ -78,8 +142,10 @@ pub struct Route {
     pub effective_rate: Decimal,
     /// 整个路由的总价格影响
     pub price_impact: Decimal,
-    /// 执行该路由的预估 gas 成本
+    /// 执行该路由的预估 gas 成本（以 SOL 计，含优先费）
     pub gas_estimate: Decimal,
+    /// 扣除折算为输出代币的 gas 成本后的净输出数量
+    pub net_output_amount: Decimal,
     /// 该路由在分割路由中的占比（用于分割路由）
     pub split_ratio: Option<Decimal>,
 }
@@ -97,27 +163,104 @@ pub struct SplitRoute {
     pub effective_rate: Decimal,
     /// 分割路由的总价格影响
     pub price_impact: Decimal,
-    /// 执行分割路由的总 gas 成本
+    /// 执行分割路由的总 gas 成本（以 SOL 计，含优先费）
+    pub gas_estimate: Decimal,
+    /// 扣除折算为输出代币的 gas 成本后的净输出数量
+    pub net_output_amount: Decimal,
+}
+
+/// 检测到的套利环路
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArbitrageCycle {
+    /// 起点和终点为同一代币的环路路由
+    pub route: Route,
+    /// 未扣除交易费用的毛利润（以起始代币计）
+    pub gross_profit: Decimal,
+    /// 环路上各段的交易费用（折算为起始代币）
+    pub total_fees: Decimal,
+    /// 执行该环路的预估 gas 成本（以 SOL 计）
     pub gas_estimate: Decimal,
+    /// 净利润，即输出减输入再扣除折算为起始代币的 gas 成本（以起始代币计）
+    pub net_profit: Decimal,
+}
+
+/// 兑换模式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapMode {
+    /// 固定输入数量，最大化输出
+    #[default]
+    ExactIn,
+    /// 固定输出数量，最小化所需输入
+    ExactOut,
 }
 
 /// 路由请求参数
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RouteRequest {
-    /// 输入代币符号
+    /// 输入代币符号、别名或 mint 地址
     pub input_token: String,
-    /// 输出代币符号
+    /// 输出代币符号、别名或 mint 地址
     pub output_token: String,
-    /// 输入代币数量
+    /// 输入代币数量（ExactOut 模式下忽略，可省略）
+    #[serde(default)]
     pub input_amount: Decimal,
+    /// 兑换模式，默认为固定输入
+    #[serde(default)]
+    pub swap_mode: SwapMode,
+    /// 期望的输出代币数量（仅 ExactOut 模式使用）
+    #[serde(default)]
+    pub output_amount: Option<Decimal>,
     /// 滑点容差（0-1 之间的小数）
     pub slippage_tolerance: Decimal,
     /// Bellman-Ford 算法的最大迭代次数
     pub max_iterations: usize,
+    /// 单条路由允许的最大跳数，未指定时使用路由器配置
+    #[serde(default)]
+    pub max_hops: Option<usize>,
     /// 是否启用分割路由功能
     pub enable_split_routes: bool,
     /// 分割路由的最大分割数量
     pub max_splits: Option<usize>,
+    /// 需要返回的备选路由数量（前 K 条不同的单一路径路由）
+    #[serde(default)]
+    pub max_alternatives: Option<usize>,
+    /// 对可用 DEX 平台和中间代币的限制
+    #[serde(default)]
+    pub filters: RouteFilters,
+}
+
+/// 路由搜索可使用的 DEX 平台和中间代币的限制
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RouteFilters {
+    /// 只允许使用这些 DEX 平台（名称或地址），未指定时不限制
+    #[serde(default)]
+    pub allowed_dexes: Option<Vec<String>>,
+    /// 禁止使用的 DEX 平台（名称或地址）
+    #[serde(default)]
+    pub excluded_dexes: Vec<String>,
+    /// 只允许经过这些中间代币（符号、别名或 mint 地址，经代币注册表解析），未指定时不限制
+    #[serde(default)]
+    pub allowed_intermediate_tokens: Option<Vec<String>>,
+    /// 禁止经过的中间代币（符号、别名或 mint 地址，经代币注册表解析）
+    #[serde(default)]
+    pub excluded_intermediate_tokens: Vec<String>,
+    /// 是否只使用输入和输出代币之间的直接交易对
+    #[serde(default)]
+    pub only_direct_routes: bool,
+}
+
+impl RouteFilters {
+    /// 是否允许使用该 DEX 平台
+    pub fn allows_dex(&self, dex: &DexPlatform) -> bool {
+        let matches = |entry: &String| {
+            entry.eq_ignore_ascii_case(&dex.name) || *entry == dex.address
+        };
+        self.allowed_dexes
+            .as_ref()
+            .is_none_or(|allowed| allowed.iter().any(matches))
+            && !self.excluded_dexes.iter().any(matches)
+    }
 }
 
 /// 包含最优路径的路由响应
@@ -129,12 +272,27 @@ pub struct RouteResponse {
     pub route: Option<Route>,
     /// 找到的分割路由（如果启用且找到）
     pub split_route: Option<SplitRoute>,
+    /// 按输出从高到低排列的备选单一路径路由，首条失败时可依次回退
+    #[serde(default)]
+    pub alternatives: Vec<Route>,
+    /// ExactOut 模式下按滑点容差放宽后的最大输入数量
+    #[serde(default)]
+    pub max_input_amount: Option<Decimal>,
     /// 路由查找的执行时间（毫秒）
     pub execution_time_ms: u64,
     /// 实际使用的迭代次数
     pub iterations_used: usize,
 }
 
+/// 单一路由搜索的结果
+#[derive(Debug, Clone)]
+pub struct RouteSearchResult {
+    /// 找到的路由（如果找到）
+    pub route: Option<Route>,
+    /// 搜索实际执行的 Bellman-Ford 迭代次数
+    pub iterations: usize,
+}
+
 /// 路由图中的节点，带距离跟踪
 #[derive(Debug, Clone)]
 pub struct GraphNode {
@@ -142,25 +300,21 @@ pub struct GraphNode {
     pub token: Token,
     /// Bellman-Ford 算法中的距离值
     pub distance: f64,
-    /// 前驱节点的代币地址（用于路径重建）
-    pub predecessor: Option<String>,
+    /// 松弛时胜出的边（池子、DEX 和费率档位），路径重建时直接使用
+    pub predecessor_edge: Option<Edge>,
     /// 到达该节点时的最优代币数量
     pub best_amount: Decimal,
-    /// 该节点已使用的流动性
-    pub liquidity_used: Decimal,
 }
 
 /// Bellman-Ford 迭代状态
 #[derive(Debug, Clone)]
 pub struct IterationState {
-    /// 图中所有节点的当前状态
-    pub nodes: HashMap<String, GraphNode>,
+    /// 按跳数分层的节点状态，第 k 层记录恰好经过 k 跳到达各节点的最优状态
+    pub layers: Vec<HashMap<String, GraphNode>>,
     /// 当前迭代是否有改进
     pub improved: bool,
     /// 当前迭代次数
     pub iteration: usize,
-    /// 目前找到的最优路由
-    pub best_route: Option<Route>,
 }
 
 /// 获取实时价格的报价请求
@@ -174,6 +328,26 @@ pub struct QuoteRequest {
     pub amount: Decimal,
     /// 请求报价的 DEX 平台名称
     pub dex_platform: String,
+    /// 指定报价的池子，未指定时由提供者选择该交易对的池子
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool_id: Option<String>,
+    /// 输入代币的 mint 地址，指定时优先于符号
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_mint: Option<String>,
+    /// 输出代币的 mint 地址，指定时优先于符号
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_mint: Option<String>,
+}
+
+/// 单条边刷新得到的市场数据
+#[derive(Debug, Clone)]
+pub struct EdgeMarketData {
+    /// 边所在的池子标识
+    pub pool_id: String,
+    /// 边的输入代币地址
+    pub from_token: String,
+    /// 刷新得到的报价，`None` 表示刷新失败
+    pub quote: Option<QuoteResponse>,
 }
 
 /// 包含价格信息的报价响应
@@ -181,7 +355,7 @@ pub struct QuoteRequest {
 pub struct QuoteResponse {
     /// 输入代币数量
     pub input_amount: Decimal,
-    /// 输出代币数量
+    /// 输出代币数量（已扣除交易费用）
     pub output_amount: Decimal,
     /// 当前汇率
     pub exchange_rate: Decimal,
@@ -198,27 +372,54 @@ pub struct QuoteResponse {
 pub struct RouterConfig {
     /// Bellman-Ford 算法的最大迭代次数
     pub max_iterations: usize,
+    /// 单条路由的默认最大跳数（受 Solana 交易大小限制）
+    pub max_hops: usize,
     /// 最小流动性阈值，低于此值的边将被忽略
     pub min_liquidity_threshold: Decimal,
     /// 最大价格影响阈值，超过此值的路由将被拒绝
     pub max_price_impact: Decimal,
     /// 每笔交易的 gas 价格（以 SOL 为单位）
     pub gas_price: Decimal,
+    /// 每笔交易与兑换无关的基础计算单元消耗
+    pub base_compute_units: u64,
+    /// 每一跳兑换消耗的计算单元
+    pub compute_units_per_hop: u64,
+    /// 每个计算单元的优先费价格（micro-lamports）
+    pub priority_fee_micro_lamports: u64,
+    /// 支付 gas 的原生代币（Wrapped SOL）地址，gas 成本经图中价格从该代币折算
+    pub native_token_address: String,
     /// 是否启用缓存功能
     pub enable_caching: bool,
     /// 缓存条目的生存时间（秒）
     pub cache_ttl_seconds: u64,
+    /// 路由缓存的最大条目数，超出时淘汰最久未使用的条目
+    pub route_cache_capacity: usize,
+    /// 后台刷新市场数据的间隔（秒）
+    pub market_data_refresh_interval_seconds: u64,
+    /// 刷新市场数据时同时进行的报价请求数
+    pub market_data_refresh_concurrency: usize,
+    /// 路由搜索是否跳过最近一次刷新失败的边
+    pub exclude_stale_edges: bool,
 }
 
 impl Default for RouterConfig {
     fn default() -> Self {
         Self {
             max_iterations: 5,
+            max_hops: 3,
             min_liquidity_threshold: dec!(100.0),
             max_price_impact: dec!(0.05), // 5%
             gas_price: dec!(0.000005), // 每笔交易的 SOL
+            base_compute_units: 50_000,
+            compute_units_per_hop: 100_000,
+            priority_fee_micro_lamports: 50_000,
+            native_token_address: "So11111111111111111111111111111111111111112".to_string(),
             enable_caching: true,
             cache_ttl_seconds: 30,
+            route_cache_capacity: 10_000,
+            market_data_refresh_interval_seconds: 60,
+            market_data_refresh_concurrency: 16,
+            exclude_stale_edges: false,
         }
     }
 } 
\ No newline at end of file
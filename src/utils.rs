@@ -1,15 +1,16 @@
+use crate::curve::{ConstantProductCurve, PoolCurve};
+use crate::registry::TokenRecord;
+use crate::routing::RouteAnalysis;
 use crate::types::*;
+use log::warn;
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
-use std::collections::HashMap;
-use std::str::FromStr;
 
 /// Metis 路由算法的数学工具
 /// 
 /// 提供各种数学计算功能，包括：
 /// - 汇率权重计算
 /// - 价格影响计算
-/// - 分割比率计算
 /// - 滑点边界计算
 pub struct MathUtils;
 
@@ -42,35 +43,6 @@ impl MathUtils {
         base_rate * (dec!(1) - fee_rate) * (dec!(1) - price_impact)
     }
 
-    /// 计算路由分割的最优分割比率
-    /// 
-    /// # 参数
-    /// * `num_splits` - 分割数量（1-10）
-    /// 
-    /// # 返回值
-    /// * `Vec<Decimal>` - 分割比率列表，总和为 1.0
-    pub fn calculate_split_ratios(num_splits: usize) -> Vec<Decimal> {
-        match num_splits {
-            1 => vec![dec!(1.0)],
-            2 => vec![dec!(0.6), dec!(0.4)],
-            3 => vec![dec!(0.5), dec!(0.3), dec!(0.2)],
-            4 => vec![dec!(0.4), dec!(0.25), dec!(0.2), dec!(0.15)],
-            _ => {
-                // 对于更多分割，使用指数衰减
-                let mut ratios = Vec::new();
-                let base_ratio = dec!(0.4);
-                for i in 0..num_splits {
-                    let decay_factor = dec!(0.7).to_string().parse::<f64>().unwrap_or(0.7).powi(i as i32);
-                    let ratio = base_ratio * Decimal::from_str(&decay_factor.to_string()).unwrap_or(dec!(0.7));
-                    ratios.push(ratio);
-                }
-                // 标准化为总和为 1.0
-                let total: Decimal = ratios.iter().sum();
-                ratios.iter().map(|&r| r / total).collect()
-            }
-        }
-    }
-
     /// 使用恒定乘积 AMM 公式计算价格影响
     /// 
     /// # 参数
@@ -86,10 +58,10 @@ impl MathUtils {
         reserve_out: Decimal,
     ) -> Decimal {
         // 恒定乘积公式：(x + dx) * (y - dy) = x * y
-        // dy = y * dx / (x + dx)
-        let _output_amount = reserve_out * input_amount / (reserve_in + input_amount);
-        let price_impact = input_amount / (reserve_in + input_amount);
-        price_impact
+        // 价格影响 = dx / (x + dx)
+        ConstantProductCurve::new(reserve_in, reserve_out)
+            .price_impact(input_amount)
+            .unwrap_or(dec!(1))
     }
 
     /// 计算滑点容差边界
@@ -126,6 +98,19 @@ impl MathUtils {
         let total_gas = base_gas_per_dex * Decimal::from(num_dex_interactions);
         total_gas * gas_price
     }
+
+    /// 计算按计算单元收取的优先费
+    /// 
+    /// # 参数
+    /// * `compute_units` - 交易消耗的计算单元数
+    /// * `micro_lamports_per_unit` - 每个计算单元的优先费价格（micro-lamports）
+    /// 
+    /// # 返回值
+    /// * `Decimal` - 优先费（以 SOL 计）
+    pub fn calculate_priority_fee(compute_units: u64, micro_lamports_per_unit: u64) -> Decimal {
+        let micro_lamports = Decimal::from(compute_units) * Decimal::from(micro_lamports_per_unit);
+        micro_lamports / dec!(1_000_000) / dec!(1_000_000_000)
+    }
 }
 
 /// 用于显示路由信息的格式化工具
@@ -135,6 +120,7 @@ impl MathUtils {
 /// - 货币格式化
 /// - 路由摘要格式化
 /// - 分割路由摘要格式化
+/// - 报价、路由分析、代币和边的摘要格式化
 pub struct FormatUtils;
 
 impl FormatUtils {
@@ -184,6 +170,9 @@ impl FormatUtils {
                                  Self::format_percentage(route.price_impact)));
         summary.push_str(&format!("Gas 成本: {}\n", 
                                  Self::format_currency(route.gas_estimate, "SOL")));
+        summary.push_str(&format!("扣除 Gas 后净输出: {}\n", 
+                                 Self::format_currency(route.net_output_amount,
+                                 &route.segments.last().unwrap().to_token.symbol)));
         summary.push_str(&format!("跳数: {}", route.segments.len()));
         summary
     }
@@ -205,9 +194,10 @@ impl FormatUtils {
                                  Self::format_percentage(split_route.price_impact)));
         summary.push_str(&format!("总 Gas 成本: {}\n", 
                                  Self::format_currency(split_route.gas_estimate, "SOL")));
+        summary.push_str(&format!("扣除 Gas 后净输出: {}\n", split_route.net_output_amount));
         
         for (i, route) in split_route.routes.iter().enumerate() {
-            summary.push_str(&format!("\n路由 {}: {} -> {} ({}%)", 
+            summary.push_str(&format!("\n路由 {}: {} -> {} ({})", 
                                      i + 1,
                                      route.segments.first().unwrap().from_token.symbol,
                                      route.segments.last().unwrap().to_token.symbol,
@@ -215,6 +205,86 @@ impl FormatUtils {
         }
         summary
     }
+
+    /// 格式化单个 DEX 报价摘要
+    /// 
+    /// # 参数
+    /// * `dex_platform` - 报价来源的 DEX 平台
+    /// * `request` - 报价请求
+    /// * `quote` - 报价响应
+    /// 
+    /// # 返回值
+    /// * `String` - 单行报价摘要
+    pub fn format_quote_summary(dex_platform: &str, request: &QuoteRequest, quote: &QuoteResponse) -> String {
+        format!("{}: {} -> {} (价格影响: {}, 费用: {})",
+                dex_platform,
+                Self::format_currency(quote.input_amount, &request.input_token),
+                Self::format_currency(quote.output_amount, &request.output_token),
+                Self::format_percentage(quote.price_impact),
+                Self::format_currency(quote.fee_amount, &request.input_token))
+    }
+
+    /// 格式化路由分析结果
+    /// 
+    /// # 参数
+    /// * `analysis` - 路由分析结果
+    /// 
+    /// # 返回值
+    /// * `String` - 格式化的分析摘要，包含改进建议
+    pub fn format_route_analysis(analysis: &RouteAnalysis) -> String {
+        let mut summary = String::new();
+        summary.push_str(&format!("总跳数: {}\n", analysis.total_hops));
+        summary.push_str(&format!("平均价格影响: {}\n", 
+                                 Self::format_percentage(analysis.avg_price_impact)));
+        summary.push_str(&format!("总费用: {}\n", analysis.total_fees));
+        summary.push_str(&format!("效率分数: {:.2}", analysis.efficiency_score));
+        
+        if !analysis.recommendations.is_empty() {
+            summary.push_str("\n建议:");
+            for rec in &analysis.recommendations {
+                summary.push_str(&format!("\n- {}", rec));
+            }
+        }
+        summary
+    }
+
+    /// 格式化代币记录用于列表显示
+    /// 
+    /// # 参数
+    /// * `record` - 代币注册表中的记录
+    /// 
+    /// # 返回值
+    /// * `String` - 单行代币摘要，包含地址、小数位、验证标记和别名
+    pub fn format_token_summary(record: &TokenRecord) -> String {
+        let mut summary = format!("{:<8} {} (小数位: {})", 
+                                  record.token.symbol, record.token.address, record.token.decimals);
+        if record.verified {
+            summary.push_str(" [已验证]");
+        }
+        if !record.aliases.is_empty() {
+            summary.push_str(&format!(" 别名: {}", record.aliases.join(", ")));
+        }
+        summary
+    }
+
+    /// 格式化边用于列表显示
+    /// 
+    /// # 参数
+    /// * `edge` - 路由图中的边
+    /// 
+    /// # 返回值
+    /// * `String` - 单行边摘要，包含交易对、DEX、池子、汇率、费率和流动性
+    pub fn format_edge_summary(edge: &Edge) -> String {
+        format!("{} -> {} via {} [{}] 汇率: {} 费率: {} 流动性: {}{}",
+                edge.from_token.symbol,
+                edge.to_token.symbol,
+                edge.dex_platform.name,
+                edge.pool_id,
+                edge.exchange_rate,
+                Self::format_percentage(edge.dex_platform.fee_rate),
+                edge.liquidity,
+                if edge.stale { " (数据过期)" } else { "" })
+    }
 }
 
 /// 路由请求和响应的验证工具
@@ -234,8 +304,17 @@ impl ValidationUtils {
     /// # 返回值
     /// * `Result<(), String>` - 验证结果，错误时返回错误信息
     pub fn validate_route_request(request: &RouteRequest) -> Result<(), String> {
-        if request.input_amount <= dec!(0) {
-            return Err("输入数量必须为正数".to_string());
+        match request.swap_mode {
+            SwapMode::ExactIn => {
+                if request.input_amount <= dec!(0) {
+                    return Err("输入数量必须为正数".to_string());
+                }
+            }
+            SwapMode::ExactOut => {
+                if request.output_amount.is_none_or(|amount| amount <= dec!(0)) {
+                    return Err("ExactOut 模式的输出数量必须为正数".to_string());
+                }
+            }
         }
 
         if request.slippage_tolerance <= dec!(0) || request.slippage_tolerance >= dec!(1) {
@@ -246,6 +325,14 @@ impl ValidationUtils {
             return Err("最大迭代次数必须大于 0".to_string());
         }
 
+        if request.max_hops == Some(0) {
+            return Err("最大跳数必须大于 0".to_string());
+        }
+
+        if request.filters.allowed_dexes.as_ref().is_some_and(|dexes| dexes.is_empty()) {
+            return Err("允许的 DEX 平台列表不能为空".to_string());
+        }
+
         if request.input_token == request.output_token {
             return Err("输入和输出代币必须不同".to_string());
         }
@@ -373,14 +460,16 @@ impl CacheUtils {
     /// * `request` - 路由请求
     /// 
     /// # 返回值
-    /// * `String` - 缓存键
-    pub fn generate_route_cache_key(request: &RouteRequest) -> String {
-        format!("route:{}:{}:{}:{}:{}", 
-                request.input_token,
-                request.output_token,
-                request.input_amount,
-                request.slippage_tolerance,
-                request.enable_split_routes)
+    /// * `Option<String>` - 缓存键；请求无法序列化时返回 `None`，调用方应跳过缓存
+    pub fn generate_route_cache_key(request: &RouteRequest) -> Option<String> {
+        // 请求的所有字段都会影响结果，序列化整个请求以免不同请求共用同一条目
+        match serde_json::to_string(request) {
+            Ok(request_json) => Some(format!("route:{}", request_json)),
+            Err(e) => {
+                warn!("⚠️  无法序列化路由请求，跳过缓存: {}", e);
+                None
+            }
+        }
     }
 
     /// 为报价请求生成缓存键